@@ -0,0 +1,211 @@
+use crate::customer::Customer;
+use crate::event::hour_of_day;
+use crate::scenario::Staffing;
+
+use std::collections::VecDeque;
+
+// Bomberos de turno, cuántos están ocupados y los clientes que esperan uno en
+// el surtidor para empezar a cargar.
+pub struct Attendants {
+    pub limited: bool,
+    pub on_duty: usize,
+    pub busy: usize,
+    waiting: VecDeque<(Customer, usize, f64)>,
+    pub stats: AttendantStats,
+}
+
+impl Attendants {
+    pub fn new(staffing: &Staffing) -> Self {
+        Attendants {
+            limited: staffing.limited(),
+            on_duty: 0,
+            busy: 0,
+            waiting: VecDeque::new(),
+            stats: AttendantStats::new(0.0),
+        }
+    }
+
+    // Toma un bombero si hay uno libre.
+    pub fn seize(&mut self, time: f64) -> bool {
+        if !self.limited {
+            return true;
+        }
+        if self.busy >= self.on_duty {
+            return false;
+        }
+        self.busy += 1;
+        self.stats.seized += 1;
+        self.stats.update(time, self.on_duty, self.busy);
+        true
+    }
+
+    pub fn release(&mut self, time: f64) {
+        if self.limited {
+            self.busy -= 1;
+            self.stats.released += 1;
+            self.stats.update(time, self.on_duty, self.busy);
+        }
+    }
+
+    // Cambio de turno o colación. Los que salen terminan antes la atención en
+    // curso, así que `busy` puede superar por un rato a `on_duty`.
+    pub fn set_on_duty(&mut self, on_duty: usize, time: f64) {
+        self.on_duty = on_duty;
+        self.stats.update(time, self.on_duty, self.busy);
+    }
+
+    pub fn wait(&mut self, customer: Customer, station: usize, time: f64) {
+        self.waiting.push_back((customer, station, time));
+    }
+
+    // Siguiente cliente en espera, tomando para él un bombero libre.
+    pub fn next_waiting(&mut self, time: f64) -> Option<(Customer, usize, f64)> {
+        if self.waiting.is_empty() || !self.seize(time) {
+            return None;
+        }
+        self.waiting.pop_front()
+    }
+}
+
+// Minutos-bombero de turno y ocupados, acumulados por hora del reloj.
+#[derive(Clone, Debug)]
+pub struct AttendantStats {
+    last_time: f64,
+    on_duty: usize,
+    busy: usize,
+    pub on_duty_minutes: [f64; 24],
+    pub busy_minutes: [f64; 24],
+    pub elapsed: [f64; 24],
+    // Veces que se tomó y se soltó un bombero en toda la corrida, calentamiento
+    // incluido: la diferencia son los que siguen atendiendo.
+    pub seized: u64,
+    pub released: u64,
+}
+
+impl AttendantStats {
+    fn new(start: f64) -> Self {
+        AttendantStats {
+            last_time: start,
+            on_duty: 0,
+            busy: 0,
+            on_duty_minutes: [0.0; 24],
+            busy_minutes: [0.0; 24],
+            elapsed: [0.0; 24],
+            seized: 0,
+            released: 0,
+        }
+    }
+
+    fn update(&mut self, time: f64, on_duty: usize, busy: usize) {
+        self.advance(time);
+        self.on_duty = on_duty;
+        self.busy = busy;
+    }
+
+    // Reparte el intervalo desde el último cambio entre las horas que cruza.
+    fn advance(&mut self, time: f64) {
+        let mut from = self.last_time;
+        while from < time {
+            let hour = hour_of_day(from) as usize;
+            let until = (((from / 60.0).floor() + 1.0) * 60.0).min(time);
+            let minutes = until - from;
+            self.elapsed[hour] += minutes;
+            self.on_duty_minutes[hour] += self.on_duty as f64 * minutes;
+            self.busy_minutes[hour] += self.busy.min(self.on_duty) as f64 * minutes;
+            from = until;
+        }
+        self.last_time = time;
+    }
+
+    // Reparte lo pendiente hasta `time` y borra los minutos de turno y de
+    // ocupación por hora, que vuelven a acumularse desde ahí.
+    pub fn reset(&mut self, time: f64) {
+        self.advance(time);
+        self.on_duty_minutes = [0.0; 24];
+        self.busy_minutes = [0.0; 24];
+        self.elapsed = [0.0; 24];
+    }
+
+    pub fn finish(&mut self, end_time: f64) {
+        self.advance(end_time);
+    }
+
+    pub fn utilization(&self) -> Option<f64> {
+        let on_duty: f64 = self.on_duty_minutes.iter().sum();
+        if on_duty > 0.0 {
+            Some(self.busy_minutes.iter().sum::<f64>() / on_duty)
+        } else {
+            None
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::Attendants;
+    use crate::customer::Vehicle;
+    use crate::scenario::{Shift, Staffing};
+    use crate::{Customer, Fuel, PaymentMethod};
+
+    fn customer(id: u64) -> Customer {
+        Customer {
+            id,
+            arrive_time: 0.0,
+            total_time: 0.0,
+            vehicle: Vehicle::Car,
+            payment_method: PaymentMethod::Tarjeta,
+            patience: None,
+            fuel: Fuel::Gasolina93,
+            litres: None,
+        }
+    }
+
+    // Una colación deja un bombero de turno con tres surtidores atendidos: los
+    // que salen terminan su atención y nadie nuevo es atendido hasta que los
+    // ocupados bajan del único de turno.
+    #[test]
+    fn break_with_more_busy_pumps_than_attendants() {
+        let staffing = Staffing::Shifts {
+            shifts: vec![Shift {
+                start: 0.0,
+                end: 24.0,
+                attendants: 3,
+                breaks: Vec::new(),
+            }],
+            takes_payment: true,
+        };
+        let mut attendants = Attendants::new(&staffing);
+        attendants.set_on_duty(3, 0.0);
+        assert!((0..3).all(|_| attendants.seize(0.0)));
+        assert!(!attendants.seize(0.0));
+
+        attendants.set_on_duty(1, 10.0);
+        attendants.wait(customer(7), 3, 12.0);
+        attendants.release(15.0);
+        assert!(attendants.next_waiting(15.0).is_none());
+        attendants.release(20.0);
+        assert!(attendants.next_waiting(20.0).is_none());
+        attendants.release(25.0);
+        let (waiting, station, since) = attendants.next_waiting(25.0).unwrap();
+        assert_eq!((waiting.id, station, since), (7, 3, 12.0));
+        assert_eq!(attendants.busy, 1);
+        assert!(attendants.next_waiting(25.0).is_none());
+
+        // Los ocupados de más no cuentan: la utilización no pasa de 1.
+        attendants.stats.finish(30.0);
+        let on_duty: f64 = attendants.stats.on_duty_minutes.iter().sum();
+        let busy: f64 = attendants.stats.busy_minutes.iter().sum();
+        assert_eq!(on_duty, 3.0 * 10.0 + 20.0);
+        assert_eq!(busy, 3.0 * 10.0 + 20.0);
+        assert_eq!(attendants.stats.utilization(), Some(1.0));
+    }
+
+    // Sin límite de bomberos nadie espera.
+    #[test]
+    fn unlimited_attendants_never_make_customers_wait() {
+        let mut attendants = Attendants::new(&Staffing::Unlimited);
+        assert!((0..100).all(|_| attendants.seize(0.0)));
+        assert_eq!(attendants.busy, 0);
+        assert_eq!(attendants.stats.utilization(), None);
+    }
+}
@@ -0,0 +1,155 @@
+use crate::customer::{CustomerData, CustomerRecord};
+use crate::stats::Estimate;
+
+// Umbral de autocorrelación lag-1 entre medias de lotes sobre el cual los
+// lotes no se consideran independientes.
+const MAX_LAG1: f64 = 0.2;
+
+// Estimación por medias de lotes de una sola corrida larga.
+pub struct BatchMeans {
+    pub batch_size: usize,
+    pub estimate: Estimate,
+    pub lag1: f64,
+}
+
+// Divide la serie en `batches` lotes consecutivos del mismo tamaño (las
+// observaciones sobrantes al final se descartan) y trata la media de cada
+// lote como una observación independiente.
+pub fn batch_means(series: &[f64], batches: usize, confidence: f64) -> Option<BatchMeans> {
+    if batches < 2 || series.len() < batches {
+        return None;
+    }
+    let batch_size = series.len() / batches;
+    let means: Vec<f64> = series
+        .chunks_exact(batch_size)
+        .take(batches)
+        .map(|batch| batch.iter().sum::<f64>() / batch_size as f64)
+        .collect();
+    Some(BatchMeans {
+        batch_size,
+        estimate: Estimate::from_sample(&means, confidence)?,
+        lag1: lag1_autocorrelation(&means),
+    })
+}
+
+pub fn lag1_autocorrelation(values: &[f64]) -> f64 {
+    let n = values.len();
+    if n < 3 {
+        return 0.0;
+    }
+    let mean = values.iter().sum::<f64>() / n as f64;
+    let variance: f64 = values.iter().map(|x| (x - mean).powi(2)).sum();
+    if variance == 0.0 {
+        return 0.0;
+    }
+    let covariance: f64 = values
+        .windows(2)
+        .map(|pair| (pair[0] - mean) * (pair[1] - mean))
+        .sum();
+    covariance / variance
+}
+
+// Serie de una medida de los clientes atendidos, en orden de salida.
+pub fn departure_series<F>(customer_data: &CustomerData, measure: F) -> Vec<f64>
+where
+    F: Fn(&CustomerRecord) -> Option<f64>,
+{
+    let mut observations: Vec<(f64, f64)> = customer_data
+        .values()
+        .filter(|record| record.served)
+        .filter_map(|record| Some((record.departure?, measure(record)?)))
+        .collect();
+    observations.sort_by(|a, b| a.0.total_cmp(&b.0));
+    observations.into_iter().map(|(_, value)| value).collect()
+}
+
+pub fn print_batch_means(customer_data: &CustomerData, batches: usize, confidence: f64) {
+    type Measure = fn(&CustomerRecord) -> Option<f64>;
+    let measures: [(&str, Measure); 2] = [
+        ("Espera en cola (min)", CustomerRecord::wait_time),
+        ("Tiempo total (min)", CustomerRecord::total_time),
+    ];
+
+    println!(
+        "Medias de lotes ({} lotes, IC {:.0}%):",
+        batches,
+        confidence * 100.0
+    );
+    for (name, measure) in measures {
+        let series = departure_series(customer_data, measure);
+        match batch_means(&series, batches, confidence) {
+            Some(result) => {
+                println!(
+                    "{:<22} | media {:.4} | IC [{:.4}, {:.4}] | lote de {} | lag-1 {:.3}",
+                    name,
+                    result.estimate.mean,
+                    result.estimate.lower(),
+                    result.estimate.upper(),
+                    result.batch_size,
+                    result.lag1
+                );
+                if result.lag1.abs() > MAX_LAG1 {
+                    println!(
+                        "  Advertencia: las medias de lotes están correlacionadas (|lag-1| > {}); use menos lotes o una corrida más larga.",
+                        MAX_LAG1
+                    );
+                }
+            }
+            None => println!(
+                "{:<22} | insuficientes observaciones ({}) para {} lotes",
+                name,
+                series.len(),
+                batches
+            ),
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::{batch_means, lag1_autocorrelation};
+
+    #[test]
+    fn constant_series_has_zero_width() {
+        let result = batch_means(&[3.0; 100], 10, 0.95).unwrap();
+        assert_eq!(result.batch_size, 10);
+        assert_eq!(result.estimate.n, 10);
+        assert_eq!(result.estimate.mean, 3.0);
+        assert_eq!(result.estimate.std_dev, 0.0);
+        assert_eq!(result.estimate.half_width, 0.0);
+        assert_eq!(result.lag1, 0.0);
+    }
+
+    #[test]
+    fn linear_series_batches() {
+        // 0..23 en 4 lotes de 5: medias 2, 7, 12 y 17; sobran 20, 21 y 22.
+        let series: Vec<f64> = (0..23).map(f64::from).collect();
+        let result = batch_means(&series, 4, 0.95).unwrap();
+        assert_eq!(result.batch_size, 5);
+        assert_eq!(result.estimate.n, 4);
+        assert!((result.estimate.mean - 9.5).abs() < 1e-12);
+        assert!((result.estimate.std_dev - (125.0f64 / 3.0).sqrt()).abs() < 1e-12);
+        // Desvíos -7.5, -2.5, 2.5, 7.5: covarianza 31.25 sobre 125.
+        assert!((result.lag1 - 0.25).abs() < 1e-12);
+    }
+
+    #[test]
+    fn alternating_series_is_negatively_correlated() {
+        let series: Vec<f64> = (0..10)
+            .map(|i| if i % 2 == 0 { 1.0 } else { -1.0 })
+            .collect();
+        assert!((lag1_autocorrelation(&series) + 0.9).abs() < 1e-12);
+        assert_eq!(lag1_autocorrelation(&[1.0, 2.0]), 0.0);
+    }
+
+    #[test]
+    fn too_few_observations_per_batch() {
+        assert!(batch_means(&[1.0, 2.0, 3.0], 5, 0.95).is_none());
+        assert!(batch_means(&[1.0; 10], 1, 0.95).is_none());
+        // Justo una observación por lote todavía alcanza.
+        assert_eq!(
+            batch_means(&[1.0, 2.0, 3.0], 3, 0.95).unwrap().batch_size,
+            1
+        );
+    }
+}
@@ -0,0 +1,88 @@
+use crate::customer::Customer;
+use crate::stats::TimeWeighted;
+
+use std::collections::VecDeque;
+
+// Caja de la tienda: cajeros ocupados y la fila de clientes que vienen a
+// pagar desde el surtidor, cada uno con el surtidor que sigue ocupando.
+#[derive(Clone)]
+pub struct Counter {
+    pub cashiers: usize,
+    pub busy: usize,
+    queue: VecDeque<(Customer, usize, f64)>,
+    pub queue_length: TimeWeighted,
+    pub occupied: TimeWeighted,
+    pub payments: u64,
+}
+
+impl Counter {
+    pub fn new(cashiers: usize) -> Self {
+        Counter {
+            cashiers,
+            busy: 0,
+            queue: VecDeque::new(),
+            queue_length: TimeWeighted::new(0.0),
+            occupied: TimeWeighted::new(0.0),
+            payments: 0,
+        }
+    }
+
+    // Toma un cajero si hay uno libre.
+    pub fn seize(&mut self, time: f64) -> bool {
+        if self.busy >= self.cashiers {
+            return false;
+        }
+        self.busy += 1;
+        self.payments += 1;
+        self.occupied.update(time, self.busy as f64);
+        true
+    }
+
+    pub fn release(&mut self, time: f64) {
+        self.busy -= 1;
+        self.occupied.update(time, self.busy as f64);
+    }
+
+    pub fn wait(&mut self, customer: Customer, station: usize, time: f64) {
+        self.queue.push_back((customer, station, time));
+        self.queue_length.update(time, self.queue.len() as f64);
+    }
+
+    // Clientes en la fila, sin contar a los que están pagando.
+    pub fn waiting(&self) -> usize {
+        self.queue.len()
+    }
+
+    // Siguiente de la fila, tomando para él un cajero libre.
+    pub fn next_waiting(&mut self, time: f64) -> Option<(Customer, usize, f64)> {
+        if self.queue.is_empty() || !self.seize(time) {
+            return None;
+        }
+        let next = self.queue.pop_front();
+        self.queue_length.update(time, self.queue.len() as f64);
+        next
+    }
+
+    // La fila y la ocupación de la caja se integran desde `time` con su
+    // valor actual; los cobros vuelven a cero.
+    pub fn reset(&mut self, time: f64) {
+        self.queue_length.restart(time);
+        self.occupied.restart(time);
+        self.payments = 0;
+    }
+
+    pub fn utilization(&self, end_time: f64) -> f64 {
+        self.occupied.mean(end_time) / self.cashiers as f64
+    }
+
+    pub fn print(&self, end_time: f64) {
+        println!(
+            "Caja: {} cobros, utilización {:.1}% ({} cajeros), fila promedio {:.3} (máx. {}).",
+            self.payments,
+            self.utilization(end_time) * 100.0,
+            self.cashiers,
+            self.queue_length.mean(end_time),
+            self.queue_length.max()
+        );
+    }
+}
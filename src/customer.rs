@@ -1,4 +1,9 @@
-use rand::Rng;
+use crate::scenario::Scenario;
+use crate::SimRng;
+
+use serde::Deserialize;
+
+use std::collections::BTreeMap;
 use std::fmt;
 
 #[derive(Clone)]
@@ -6,14 +11,106 @@ pub struct Customer {
     pub id: u64,
     pub arrive_time: f64,
     pub total_time: f64,
+    pub vehicle: Vehicle,
     pub payment_method: PaymentMethod,
+    // Minutos que está dispuesto a esperar en la cola; None si no abandona.
+    pub patience: Option<f64>,
+    pub fuel: Fuel,
+    // Litros a cargar; None si el escenario no modela litros.
+    pub litres: Option<f64>,
 }
 
-#[derive(Clone, Debug, PartialEq)]
+#[derive(Clone, Copy, Debug, PartialEq, Eq, PartialOrd, Ord, Hash, Deserialize)]
+#[serde(rename_all = "snake_case")]
 pub enum PaymentMethod {
     Efectivo,
     Tarjeta,
-    CopecApp
+    CopecApp,
+}
+
+// Categorías de vehículo.
+#[derive(Clone, Copy, Debug, PartialEq, Eq, PartialOrd, Ord, Hash, Deserialize)]
+#[serde(rename_all = "snake_case")]
+pub enum Vehicle {
+    Car,
+    Motorcycle,
+    Van,
+    Truck,
+}
+
+impl Vehicle {
+    pub const ALL: [Vehicle; 4] = [
+        Vehicle::Car,
+        Vehicle::Motorcycle,
+        Vehicle::Van,
+        Vehicle::Truck,
+    ];
+
+    // Nombre de la categoría en el escenario.
+    pub fn key(&self) -> &'static str {
+        match self {
+            Vehicle::Car => "car",
+            Vehicle::Motorcycle => "motorcycle",
+            Vehicle::Van => "van",
+            Vehicle::Truck => "truck",
+        }
+    }
+}
+
+impl fmt::Display for Vehicle {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        let name = match self {
+            Vehicle::Car => "Auto",
+            Vehicle::Motorcycle => "Moto",
+            Vehicle::Van => "Furgón",
+            Vehicle::Truck => "Camión",
+        };
+        write!(f, "{}", name)
+    }
+}
+
+// Combustibles que vende la estación.
+#[derive(Clone, Copy, Debug, PartialEq, Eq, PartialOrd, Ord, Hash, Deserialize)]
+pub enum Fuel {
+    #[serde(rename = "93")]
+    Gasolina93,
+    #[serde(rename = "95")]
+    Gasolina95,
+    #[serde(rename = "97")]
+    Gasolina97,
+    #[serde(rename = "diesel")]
+    Diesel,
+}
+
+impl Fuel {
+    pub const ALL: [Fuel; 4] = [
+        Fuel::Gasolina93,
+        Fuel::Gasolina95,
+        Fuel::Gasolina97,
+        Fuel::Diesel,
+    ];
+
+    // Posición en `Fuel::ALL`.
+    pub fn index(&self) -> usize {
+        match self {
+            Fuel::Gasolina93 => 0,
+            Fuel::Gasolina95 => 1,
+            Fuel::Gasolina97 => 2,
+            Fuel::Diesel => 3,
+        }
+    }
+}
+
+impl fmt::Display for Fuel {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        let name = match self {
+            Fuel::Gasolina93 => "93",
+            Fuel::Gasolina95 => "95",
+            Fuel::Gasolina97 => "97",
+            Fuel::Diesel => "Diésel",
+        };
+        write!(f, "{}", name)
+    }
 }
 
 impl fmt::Display for PaymentMethod {
@@ -21,28 +118,292 @@ impl fmt::Display for PaymentMethod {
         let string_representation = match self {
             PaymentMethod::Efectivo => "EFECTIVO",
             PaymentMethod::Tarjeta => "TARJETA",
-            PaymentMethod::CopecApp => "APP"
+            PaymentMethod::CopecApp => "APP",
         };
         write!(f, "{}", string_representation.to_uppercase())
     }
 }
 
 impl Customer {
-    pub fn new(id: u64, arrive_time: f64) -> Self {
-        let payment_methods = [
-            PaymentMethod::Efectivo,
-            PaymentMethod::Tarjeta,
-            PaymentMethod::CopecApp
-        ];
-
-        let rng = rand::thread_rng().gen_range(0..payment_methods.len());
-        let payment_method = payment_methods[rng].clone();
-
+    // Primero se sortea la categoría; ella puede cambiar la mezcla de pagos,
+    // los combustibles posibles y los litros.
+    pub fn new(id: u64, arrive_time: f64, scenario: &Scenario, rng: &mut SimRng) -> Self {
+        let vehicle = scenario.vehicles.sample_vehicle(&mut rng.vehicle_choice);
+        let params = scenario.vehicles.params(vehicle);
+        let payment_method = match &params.payment {
+            Some(shares) => scenario
+                .payment
+                .sample_method_from(shares.shares(), &mut rng.payment_choice),
+            None => scenario.payment.sample_method(&mut rng.payment_choice),
+        };
+        let patience = scenario.reneging.sample_patience(&mut rng.patience);
+        let fuel = if params.fuels.is_empty() {
+            scenario.fuel.sample_fuel(&mut rng.fuel_choice)
+        } else {
+            scenario
+                .fuel
+                .sample_fuel_among(&params.fuels, &mut rng.fuel_choice)
+        };
+        let litres = match &params.litres {
+            Some(litres) => litres.sample(&mut rng.litres),
+            None => scenario.dispensing.sample_litres(&mut rng.litres),
+        };
         Customer {
             id,
             arrive_time,
             total_time: 0.0,
+            vehicle,
             payment_method,
+            patience,
+            fuel,
+            litres,
         }
     }
-}
\ No newline at end of file
+}
+
+// Registro de un cliente para las estadísticas. Cada instante se guarda por
+// separado; los que aún no ocurren quedan en None.
+#[derive(Clone, Debug)]
+pub struct CustomerRecord {
+    pub vehicle: Vehicle,
+    pub payment_method: PaymentMethod,
+    pub fuel: Fuel,
+    pub arrival: f64,
+    pub queue_entry: Option<f64>,
+    pub service_start: Option<f64>,
+    pub refuel_end: Option<f64>,
+    pub payment_end: Option<f64>,
+    pub departure: Option<f64>,
+    pub station: Option<usize>,
+    pub litres: Option<f64>,
+    pub served: bool,
+    // Se fue sin hacer fila (venta perdida).
+    pub balked: bool,
+    // Se fue sin cargar porque su combustible se agotó, al llegar o esperando
+    // en la cola.
+    pub stocked_out: bool,
+    // Se fue porque ningún surtidor de la estación vende su combustible o
+    // admite su vehículo. No es balking: no tenía cola que evaluar.
+    pub unserved: bool,
+    // Abandonó la cola al agotarse su paciencia; `departure` marca el abandono.
+    pub reneged: bool,
+    // Veces que se cambió de cola.
+    pub jockeys: u32,
+    // Minutos en el surtidor esperando un bombero libre; se cuentan en la espera.
+    pub attendant_wait: f64,
+    // Pagó en la caja de la tienda, y minutos que hizo fila allí.
+    pub paid_at_counter: bool,
+    pub counter_wait: f64,
+}
+
+// Registros de todos los clientes que llegaron, ordenados por ID.
+pub type CustomerData = BTreeMap<u64, CustomerRecord>;
+
+impl CustomerRecord {
+    pub fn new(customer: &Customer, arrival: f64) -> Self {
+        CustomerRecord {
+            vehicle: customer.vehicle,
+            payment_method: customer.payment_method,
+            fuel: customer.fuel,
+            arrival,
+            queue_entry: None,
+            service_start: None,
+            refuel_end: None,
+            payment_end: None,
+            departure: None,
+            station: None,
+            litres: None,
+            served: false,
+            balked: false,
+            stocked_out: false,
+            unserved: false,
+            reneged: false,
+            jockeys: 0,
+            attendant_wait: 0.0,
+            paid_at_counter: false,
+            counter_wait: 0.0,
+        }
+    }
+
+    // Tiempo desde la llegada hasta llegar al surtidor. Es exactamente 0
+    // para quien encuentra un surtidor libre.
+    pub fn wait_time(&self) -> Option<f64> {
+        self.service_start
+            .map(|service_start| service_start - self.arrival)
+    }
+
+    pub fn refuel_time(&self) -> Option<f64> {
+        Some(self.refuel_end? - self.service_start?)
+    }
+
+    pub fn payment_time(&self) -> Option<f64> {
+        Some(self.payment_end? - self.refuel_end?)
+    }
+
+    // Tiempo en el surtidor: carga más pago.
+    pub fn service_time(&self) -> Option<f64> {
+        Some(self.payment_end? - self.service_start?)
+    }
+
+    // Tiempo que el auto de adelante le impidió salir tras pagar.
+    pub fn blocked_time(&self) -> Option<f64> {
+        Some(self.departure? - self.payment_end?)
+    }
+
+    // Tiempo total en el sistema, desde la llegada hasta la salida.
+    pub fn total_time(&self) -> Option<f64> {
+        self.departure.map(|departure| departure - self.arrival)
+    }
+
+    // Llegó y todavía no sale ni se fue sin ser atendido.
+    pub fn in_system(&self) -> bool {
+        !self.served && !self.balked && !self.stocked_out && !self.unserved && !self.reneged
+    }
+}
+
+// Verifica que para cada cliente que salió espera + carga + pago + bloqueo
+// == tiempo total.
+pub fn validate_customer_data(customer_data: &CustomerData) -> Result<(), String> {
+    for (id, record) in customer_data.iter().filter(|(_, record)| record.served) {
+        let (wait, refuel, payment, blocked, total) = match (
+            record.wait_time(),
+            record.refuel_time(),
+            record.payment_time(),
+            record.blocked_time(),
+            record.total_time(),
+        ) {
+            (Some(wait), Some(refuel), Some(payment), Some(blocked), Some(total)) => {
+                (wait, refuel, payment, blocked, total)
+            }
+            _ => return Err(format!("Cliente {} salió con instantes sin registrar", id)),
+        };
+        if blocked < 0.0 {
+            return Err(format!("Cliente {}: salió antes de terminar de pagar", id));
+        }
+        if (wait + refuel + payment + blocked - total).abs() > 1e-6 * total.max(1.0) {
+            return Err(format!(
+                "Cliente {}: espera {} + carga {} + pago {} + bloqueo {} != total {}",
+                id, wait, refuel, payment, blocked, total
+            ));
+        }
+    }
+    Ok(())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::{validate_customer_data, CustomerData, CustomerRecord};
+    use crate::{simulation, Customer, RunLength, Scenario, SimRng};
+
+    fn served_record(arrival: f64, service_start: f64, departure: f64) -> CustomerRecord {
+        let scenario = Scenario::default();
+        let customer = Customer::new(0, arrival, &scenario, &mut SimRng::new(1));
+        let mut record = CustomerRecord::new(&customer, arrival);
+        record.queue_entry = Some(arrival);
+        record.service_start = Some(service_start);
+        record.refuel_end = Some(service_start + 4.0);
+        record.payment_end = Some(service_start + 5.5);
+        record.departure = Some(departure);
+        record.station = Some(0);
+        record.served = true;
+        record
+    }
+
+    #[test]
+    fn validation_accepts_immediate_service() {
+        let mut customer_data = CustomerData::new();
+        // Sin espera ni bloqueo, y con espera y bloqueo.
+        customer_data.insert(0, served_record(10.0, 10.0, 15.5));
+        customer_data.insert(1, served_record(12.0, 15.0, 21.0));
+        assert_eq!(customer_data[&0].wait_time(), Some(0.0));
+        assert_eq!(customer_data[&1].blocked_time(), Some(0.5));
+        assert_eq!(validate_customer_data(&customer_data), Ok(()));
+    }
+
+    #[test]
+    fn validation_rejects_broken_accounts() {
+        let mut customer_data = CustomerData::new();
+        // Sale antes de terminar de pagar.
+        customer_data.insert(0, served_record(10.0, 10.0, 15.0));
+        assert!(validate_customer_data(&customer_data).is_err());
+        let mut record = served_record(10.0, 10.0, 15.5);
+        record.refuel_end = None;
+        customer_data.insert(0, record);
+        assert!(validate_customer_data(&customer_data).is_err());
+    }
+
+    // Con poca carga la mayoría llega a un surtidor libre; su espera es 0
+    // exacto y las cuentas cuadran igual.
+    #[test]
+    fn immediate_service_has_zero_wait() {
+        let scenario = Scenario::default();
+        let mut customer_data = CustomerData::new();
+        let summary = simulation(
+            RunLength::Minutes(600.0),
+            &mut customer_data,
+            scenario.stations,
+            &scenario,
+            1,
+            None,
+        );
+        assert_eq!(summary.inconsistency, None);
+        let immediate = customer_data
+            .values()
+            .filter(|record| record.served && record.wait_time() == Some(0.0))
+            .count();
+        assert!(immediate > 0);
+        assert_eq!(validate_customer_data(&customer_data), Ok(()));
+    }
+
+    // Cada desenlace deja registrados justo los instantes que alcanzó a vivir.
+    #[test]
+    fn record_timestamps_follow_each_outcome() {
+        let scenario: Scenario = toml::from_str(
+            r#"
+            [arrivals]
+            scale = 30.0
+
+            [balking]
+            model = "threshold"
+            length = 3
+
+            [reneging]
+            model = "exponential"
+            mean = 5.0
+            "#,
+        )
+        .unwrap();
+        let mut customer_data = CustomerData::new();
+        simulation(
+            RunLength::Minutes(600.0),
+            &mut customer_data,
+            scenario.stations,
+            &scenario,
+            1,
+            None,
+        );
+        // Atendidos, balking, abandonos y los que siguen en el sistema.
+        let mut outcomes = [0; 4];
+        for record in customer_data.values() {
+            if record.served {
+                outcomes[0] += 1;
+                assert!(record.queue_entry.is_some() && record.station.is_some());
+                assert!(record.service_start.is_some() && record.refuel_end.is_some());
+                assert!(record.payment_end.is_some() && record.departure.is_some());
+            } else if record.balked {
+                outcomes[1] += 1;
+                assert!(record.queue_entry.is_none());
+                assert!(record.service_start.is_none() && record.departure.is_none());
+            } else if record.reneged {
+                outcomes[2] += 1;
+                assert!(record.queue_entry.is_some() && record.departure.is_some());
+                assert!(record.service_start.is_none() && record.station.is_none());
+            } else {
+                outcomes[3] += 1;
+                assert!(record.in_system());
+                assert!(record.departure.is_none());
+            }
+        }
+        assert!(outcomes.iter().all(|&count| count > 0), "{:?}", outcomes);
+    }
+}
@@ -1,38 +1,146 @@
-use crate::customer::Customer;
+use crate::customer::{Customer, Fuel, PaymentMethod, Vehicle};
+
+use std::fmt;
+
+// Tipos de evento. Los eventos ligados a una estación llevan su índice.
+#[derive(Clone, Copy, Debug, PartialEq)]
+pub enum EventKind {
+    Arrive,
+    Queue,
+    Refuel { station: usize },
+    Payment { station: usize },
+    Departure { station: usize },
+    // Fin de la paciencia de un cliente en cola. Se ignora si ya llegó a un
+    // surtidor, porque el heap no permite sacar eventos ya agendados.
+    Renege,
+    // Un cliente que se cambió de cola llega al final de `queue`.
+    Rejoin { queue: usize },
+    // Llega el camión cisterna y empieza a descargar; el combustible queda
+    // cerrado hasta `TankerUnloaded`.
+    TankerArrival { fuel: Fuel },
+    TankerUnloaded { fuel: Fuel },
+    // Un surtidor sale de servicio o vuelve a él.
+    PumpDown { station: usize, cause: OutageCause },
+    PumpUp { station: usize, cause: OutageCause },
+    // Empieza o termina un turno o una colación de los bomberos.
+    StaffChange,
+    // El cliente llega a la caja de la tienda, y termina de pagar allí. Su
+    // auto sigue ocupando `station`.
+    CounterArrival { station: usize },
+    CounterDone { station: usize },
+}
+
+// Motivo por el que un surtidor queda fuera de servicio. `window` es el
+// índice en `breakdowns.maintenance`.
+#[derive(Clone, Copy, Debug, PartialEq)]
+pub enum OutageCause {
+    Failure,
+    Maintenance { window: usize },
+}
+
+impl EventKind {
+    // Orden en que se procesan los eventos del mismo instante.
+    pub fn rank(&self) -> u64 {
+        match self {
+            EventKind::Arrive => 0,
+            EventKind::Queue => 1,
+            EventKind::Refuel { .. } => 2,
+            EventKind::Payment { .. } => 3,
+            EventKind::Departure { .. } => 4,
+            EventKind::Renege => 5,
+            EventKind::Rejoin { .. } => 6,
+            EventKind::TankerArrival { .. } => 7,
+            EventKind::TankerUnloaded { .. } => 8,
+            EventKind::PumpDown { .. } => 9,
+            EventKind::PumpUp { .. } => 10,
+            EventKind::StaffChange => 11,
+            EventKind::CounterArrival { .. } => 12,
+            EventKind::CounterDone { .. } => 13,
+        }
+    }
+
+    pub fn station(&self) -> Option<usize> {
+        match self {
+            EventKind::Arrive
+            | EventKind::Queue
+            | EventKind::Renege
+            | EventKind::Rejoin { .. }
+            | EventKind::TankerArrival { .. }
+            | EventKind::TankerUnloaded { .. }
+            | EventKind::StaffChange => None,
+            EventKind::Refuel { station }
+            | EventKind::Payment { station }
+            | EventKind::Departure { station }
+            | EventKind::PumpDown { station, .. }
+            | EventKind::PumpUp { station, .. }
+            | EventKind::CounterArrival { station }
+            | EventKind::CounterDone { station } => Some(*station),
+        }
+    }
+
+    pub fn name(&self) -> &'static str {
+        match self {
+            EventKind::Arrive => "ARRIVE",
+            EventKind::Queue => "QUEUE",
+            EventKind::Refuel { .. } => "REFUEL",
+            EventKind::Payment { .. } => "PAYMENT",
+            EventKind::Departure { .. } => "DEPARTURE",
+            EventKind::Renege => "RENEGE",
+            EventKind::Rejoin { .. } => "REJOIN",
+            EventKind::TankerArrival { .. } => "TANKER",
+            EventKind::TankerUnloaded { .. } => "UNLOADED",
+            EventKind::PumpDown { .. } => "PUMP DOWN",
+            EventKind::PumpUp { .. } => "PUMP UP",
+            EventKind::StaffChange => "STAFF",
+            EventKind::CounterArrival { .. } => "COUNTER",
+            EventKind::CounterDone { .. } => "PAID",
+        }
+    }
+}
+
+impl fmt::Display for OutageCause {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            OutageCause::Failure => write!(f, "Falla"),
+            OutageCause::Maintenance { .. } => write!(f, "Mantención"),
+        }
+    }
+}
 
 pub struct Event {
-    pub id: u64,
+    pub kind: EventKind,
     pub customer: Customer,
     pub scheduled_time: f64,
-    pub chosen_queue: Option<u64>,
 }
 
 impl Event {
-    pub fn new(
-        id: u64,
-        customer: Customer,
-        scheduled_time: f64,
-        chosen_queue: Option<u64>,
-    ) -> Event {
+    pub fn new(kind: EventKind, customer: Customer, scheduled_time: f64) -> Event {
         Event {
-            id,
+            kind,
             customer,
             scheduled_time,
-            chosen_queue,
         }
     }
 
-    pub fn pretty_print(&self) -> String {
-        let event_type = match self.id {
-            0 => "ARRIVE",
-            1 => "QUEUE",
-            2 => "REFUEL",
-            3 => "PAYMENT",
-            4 => "DEPARTURE",
-            _ => "UNKNOWN",
+    // Evento de la estación que no corresponde a ningún cliente. Lleva un
+    // cliente vacío con el último ID, así queda después de los clientes del
+    // mismo instante y tipo.
+    pub fn without_customer(kind: EventKind, scheduled_time: f64) -> Event {
+        let nobody = Customer {
+            id: u64::MAX,
+            arrive_time: scheduled_time,
+            total_time: 0.0,
+            vehicle: Vehicle::Car,
+            payment_method: PaymentMethod::Efectivo,
+            patience: None,
+            fuel: Fuel::Gasolina93,
+            litres: None,
         };
+        Event::new(kind, nobody, scheduled_time)
+    }
 
-        let chosen_queue_str = match self.chosen_queue {
+    pub fn pretty_print(&self) -> String {
+        let chosen_queue_str = match self.kind.station() {
             Some(queue) => queue.to_string(),
             None => "-".to_string(),
         };
@@ -42,11 +150,24 @@ impl Event {
         format!(
             //{:<8.2} for self.scheduled_time
             "{:<8} | {:<10} | {:<8} | {:<4}",
-            scheduled_time_format, event_type, self.customer.id, chosen_queue_str
+            scheduled_time_format,
+            self.kind.name(),
+            self.customer.id,
+            chosen_queue_str
         )
     }
 }
 
+// Hora del día (0-23) de un instante de la simulación; la corrida parte a las 4 AM.
+pub fn hour_of_day(minutes: f64) -> u32 {
+    clock_hours(minutes) as u32
+}
+
+// Hora del reloj con fracción, en [0, 24).
+pub fn clock_hours(minutes: f64) -> f64 {
+    (4.0 + minutes / 60.0) % 24.0
+}
+
 fn format_time(minutes: f64) -> String {
     let hours = (4.0 + (minutes / 60.0)) % 24.0;
     let is_pm = hours >= 12.0;
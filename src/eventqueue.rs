@@ -1,33 +1,169 @@
 use crate::event::Event;
 
+use ordered_float::OrderedFloat;
+use std::cmp::{Ordering, Reverse};
+use std::collections::BinaryHeap;
+
+// Lista de eventos futuros sobre un min-heap binario. Los eventos salen por
+// tiempo agendado, luego por tipo de evento y luego por ID de cliente; el
+// contador de inserción solo desempata lo que queda igual, así el orden nunca
+// depende de cómo el heap acomoda sus elementos.
 pub struct EventQueue {
-    pub q: Vec<Event>,
+    heap: BinaryHeap<Reverse<ScheduledEvent>>,
+    inserted: u64,
+}
+
+struct ScheduledEvent {
+    key: (OrderedFloat<f64>, u64, u64, u64),
+    event: Event,
+}
+
+impl PartialEq for ScheduledEvent {
+    fn eq(&self, other: &Self) -> bool {
+        self.key == other.key
+    }
+}
+
+impl Eq for ScheduledEvent {}
+
+impl PartialOrd for ScheduledEvent {
+    fn partial_cmp(&self, other: &Self) -> Option<Ordering> {
+        Some(self.cmp(other))
+    }
+}
+
+impl Ord for ScheduledEvent {
+    fn cmp(&self, other: &Self) -> Ordering {
+        self.key.cmp(&other.key)
+    }
+}
+
+impl Default for EventQueue {
+    fn default() -> Self {
+        EventQueue::new()
+    }
 }
 
 impl EventQueue {
     pub fn new() -> EventQueue {
-        EventQueue { q: Vec::new() }
-    }
-
-    pub fn add(&mut self, event: Event) {
-        let index = self
-            .q
-            .binary_search_by(|existing_event| {
-                if existing_event.scheduled_time == event.scheduled_time {
-                    if existing_event.id == event.id {
-                        existing_event.customer.id.cmp(&event.customer.id)
-                    } else {
-                        existing_event.id.cmp(&event.id)
-                    }
-                } else {
-                    existing_event
-                        .scheduled_time
-                        .partial_cmp(&event.scheduled_time)
-                        .unwrap()
-                }
-            })
-            .unwrap_or_else(|index| index);
-
-        self.q.insert(index, event);
+        EventQueue {
+            heap: BinaryHeap::new(),
+            inserted: 0,
+        }
+    }
+
+    pub fn push(&mut self, event: Event) {
+        let key = (
+            OrderedFloat(event.scheduled_time),
+            event.kind.rank(),
+            event.customer.id,
+            self.inserted,
+        );
+        self.inserted += 1;
+        self.heap.push(Reverse(ScheduledEvent { key, event }));
+    }
+
+    pub fn pop(&mut self) -> Option<Event> {
+        self.heap.pop().map(|Reverse(scheduled)| scheduled.event)
+    }
+
+    pub fn peek(&self) -> Option<&Event> {
+        self.heap.peek().map(|Reverse(scheduled)| &scheduled.event)
+    }
+
+    pub fn len(&self) -> usize {
+        self.heap.len()
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.heap.is_empty()
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::EventQueue;
+    use crate::customer::{Customer, Fuel, PaymentMethod, Vehicle};
+    use crate::event::{Event, EventKind};
+
+    fn event(kind: EventKind, id: u64, scheduled_time: f64) -> Event {
+        let customer = Customer {
+            id,
+            arrive_time: 0.0,
+            total_time: 0.0,
+            vehicle: Vehicle::Car,
+            payment_method: PaymentMethod::Efectivo,
+            patience: None,
+            fuel: Fuel::Gasolina93,
+            litres: None,
+        };
+        Event::new(kind, customer, scheduled_time)
+    }
+
+    fn drain(queue: &mut EventQueue) -> Vec<(f64, u64)> {
+        std::iter::from_fn(|| queue.pop())
+            .map(|e| (e.scheduled_time, e.customer.id))
+            .collect()
+    }
+
+    #[test]
+    fn pops_in_scheduled_time_order() {
+        let mut queue = EventQueue::new();
+        for (id, time) in [(0, 7.5), (1, 0.25), (2, 3.0), (3, 12.0), (4, 1.0)] {
+            queue.push(event(EventKind::Arrive, id, time));
+        }
+        assert_eq!(queue.len(), 5);
+        assert_eq!(queue.peek().unwrap().scheduled_time, 0.25);
+        assert_eq!(
+            drain(&mut queue),
+            vec![(0.25, 1), (1.0, 4), (3.0, 2), (7.5, 0), (12.0, 3)]
+        );
+        assert!(queue.is_empty());
+    }
+
+    #[test]
+    fn equal_times_break_ties_by_customer_then_insertion() {
+        let mut queue = EventQueue::new();
+        queue.push(event(EventKind::Arrive, 9, 5.0));
+        // Los eventos con la misma clave salen en el orden en que se agendaron;
+        // total_time solo sirve para distinguirlos.
+        for tag in [1.0, 2.0, 3.0] {
+            let mut e = event(EventKind::Arrive, 2, 5.0);
+            e.customer.total_time = tag;
+            queue.push(e);
+        }
+        queue.push(event(EventKind::Arrive, 4, 5.0));
+        let tags: Vec<f64> = (0..3)
+            .map(|_| queue.pop().unwrap())
+            .inspect(|e| assert_eq!(e.customer.id, 2))
+            .map(|e| e.customer.total_time)
+            .collect();
+        assert_eq!(tags, vec![1.0, 2.0, 3.0]);
+        assert_eq!(drain(&mut queue), vec![(5.0, 4), (5.0, 9)]);
+    }
+
+    #[test]
+    fn simultaneous_events_follow_kind_rank_before_customer() {
+        let mut queue = EventQueue::new();
+        queue.push(event(EventKind::Departure { station: 0 }, 1, 5.0));
+        queue.push(event(EventKind::Refuel { station: 1 }, 3, 5.0));
+        queue.push(event(EventKind::Arrive, 7, 5.0));
+        queue.push(event(EventKind::Payment { station: 2 }, 0, 5.0));
+        queue.push(event(EventKind::Queue, 5, 5.0));
+        queue.push(event(EventKind::Arrive, 6, 4.0));
+        let kinds: Vec<&str> = std::iter::from_fn(|| queue.pop())
+            .map(|e| e.kind.name())
+            .collect();
+        assert_eq!(
+            kinds,
+            vec![
+                "ARRIVE",
+                "ARRIVE",
+                "QUEUE",
+                "REFUEL",
+                "PAYMENT",
+                "DEPARTURE"
+            ]
+        );
     }
 }
@@ -1,7 +1,13 @@
+use crate::attendants::AttendantStats;
+use crate::customer::{Fuel, Vehicle};
+use crate::event::hour_of_day;
+use crate::stats::Outage;
+use crate::{CustomerData, PaymentMethod};
+
 use plotly::{common::Mode, Plot, Scatter};
-use std::collections::HashMap;
+use std::collections::BTreeMap;
 
-pub fn payment_method_avg_time(customer_data: &mut HashMap<u64, (u8, f64, f64, f64, u8)>) {
+pub fn payment_method_avg_time(customer_data: &CustomerData) {
     let mut time_values_0: Vec<f64> = Vec::new();
     let mut avg_values_0: Vec<f64> = Vec::new();
 
@@ -20,33 +26,33 @@ pub fn payment_method_avg_time(customer_data: &mut HashMap<u64, (u8, f64, f64, f
     let mut sum_2: f64 = 0.0;
     let mut count_2: f64 = 0.0;
 
-    for (_, &(payment_method, _, _, time_total, departure_key)) in customer_data.iter() {
-        if departure_key == 0 {
-            continue;
-        }
-        match payment_method {
-            0 => {
+    for record in customer_data.values() {
+        let time_total = match record.total_time() {
+            Some(time_total) if record.served => time_total,
+            _ => continue,
+        };
+        match record.payment_method {
+            PaymentMethod::Efectivo => {
                 sum_0 += time_total;
                 count_0 += 1.0;
 
                 time_values_0.push(count_0);
                 avg_values_0.push(sum_0 / count_0);
             }
-            1 => {
+            PaymentMethod::Tarjeta => {
                 sum_1 += time_total;
                 count_1 += 1.0;
 
                 time_values_1.push(count_1);
                 avg_values_1.push(sum_1 / count_1);
             }
-            2 => {
+            PaymentMethod::CopecApp => {
                 sum_2 += time_total;
                 count_2 += 1.0;
 
                 time_values_2.push(count_2);
                 avg_values_2.push(sum_2 / count_2);
             }
-            _ => {}
         }
     }
 
@@ -69,8 +75,9 @@ pub fn payment_method_avg_time(customer_data: &mut HashMap<u64, (u8, f64, f64, f
 }
 
 pub fn four_vs_five_stations(
-    customer_data: &mut HashMap<u64, (u8, f64, f64, f64, u8)>,
-    customer_data_5s: &mut HashMap<u64, (u8, f64, f64, f64, u8)>,
+    customer_data: &CustomerData,
+    customer_data_5s: &CustomerData,
+    station_counts: (usize, usize),
 ) {
     let mut time_values1: Vec<f64> = Vec::new();
     let mut avg_values1: Vec<f64> = Vec::new();
@@ -78,10 +85,11 @@ pub fn four_vs_five_stations(
     let mut sum1: f64 = 0.0;
     let mut count1: f64 = 0.0;
 
-    for (_, &(_, _, _, time_total, departure_key)) in customer_data.iter() {
-        if departure_key == 0 {
-            continue;
-        }
+    for record in customer_data.values() {
+        let time_total = match record.total_time() {
+            Some(time_total) if record.served => time_total,
+            _ => continue,
+        };
         sum1 += time_total;
         count1 += 1.0;
 
@@ -98,10 +106,11 @@ pub fn four_vs_five_stations(
     let mut count2: f64 = 0.0;
 
     // Iterate over the time steps in customer_data2 and update the sum and count
-    for (_, &(_, _, _, time_total, departure_key)) in customer_data_5s.iter() {
-        if departure_key == 0 {
-            continue;
-        }
+    for record in customer_data_5s.values() {
+        let time_total = match record.total_time() {
+            Some(time_total) if record.served => time_total,
+            _ => continue,
+        };
         sum2 += time_total;
         count2 += 1.0;
 
@@ -113,10 +122,10 @@ pub fn four_vs_five_stations(
 
     let trace1 = Scatter::new(time_values1, avg_values1)
         .mode(Mode::Markers)
-        .name("4 Estaciones");
+        .name(format!("{} Estaciones", station_counts.0));
     let trace2 = Scatter::new(time_values2, avg_values2)
         .mode(Mode::Markers)
-        .name("5 Estaciones");
+        .name(format!("{} Estaciones", station_counts.1));
 
     let mut plot = Plot::new();
     plot.add_trace(trace1);
@@ -124,7 +133,7 @@ pub fn four_vs_five_stations(
     plot.write_html("cuatro_vs_cinco_estaciones.html");
 }
 
-pub fn queue_avg_waittime(customer_data: &mut HashMap<u64, (u8, f64, f64, f64, u8)>) {
+pub fn queue_avg_waittime(customer_data: &CustomerData) {
     // Promedio tiempo de simulación vs tiempo promedio espera cola
 
     let mut time_values: Vec<i64> = Vec::new();
@@ -133,10 +142,12 @@ pub fn queue_avg_waittime(customer_data: &mut HashMap<u64, (u8, f64, f64, f64, u
     let mut wait_time_sum: f64 = 0.0;
     let mut count: f64 = 0.0;
 
-    for (_, &(_, time, wait_time, _, departure_key)) in customer_data.iter() {
-        if departure_key == 0 {
-            continue;
-        }
+    for record in customer_data.values() {
+        let wait_time = match record.wait_time() {
+            Some(wait_time) if record.served => wait_time,
+            _ => continue,
+        };
+        let time = record.arrival;
         // if (departure_key == 1) {
         //     wait_time_sum += wait_time;
         //     count += 1.0;
@@ -157,15 +168,16 @@ pub fn queue_avg_waittime(customer_data: &mut HashMap<u64, (u8, f64, f64, f64, u
     plot.write_html("promedio_cola_vs_dia.html");
 }
 
-pub fn payment_method_sensitivity(customer_data: &mut HashMap<u64, (u8, f64, f64, f64, u8)>) {
-    let mut averages: HashMap<u8, f64> = HashMap::new();
-    let mut counts: HashMap<u8, usize> = HashMap::new();
+pub fn payment_method_sensitivity(customer_data: &CustomerData) {
+    let mut averages: BTreeMap<PaymentMethod, f64> = BTreeMap::new();
+    let mut counts: BTreeMap<PaymentMethod, usize> = BTreeMap::new();
 
-    // Iterate over the customer_data HashMap
-    for (_, &(payment_method, _, _, last_element, departure_key)) in customer_data.iter() {
-        if departure_key == 0 {
-            continue;
-        }
+    for record in customer_data.values() {
+        let last_element = match record.total_time() {
+            Some(time_total) if record.served => time_total,
+            _ => continue,
+        };
+        let payment_method = record.payment_method;
         // Update the sum and count for the payment method
         let sum = averages.entry(payment_method).or_insert(0.0);
         let count = counts.entry(payment_method).or_insert(0);
@@ -179,12 +191,516 @@ pub fn payment_method_sensitivity(customer_data: &mut HashMap<u64, (u8, f64, f64
         let count = *counts.get(payment_method).unwrap();
         let average = sum / (count as f64);
         match payment_method {
-            0 => println!("Tiempo promedio con Pago Efectivo = {} mins.", average),
-            1 => println!("Tiempo promedio con Pago Tarjeta  = {} mins.", average),
-            2 => println!("Tiempo promedio con Pago CopecApp = {} mins.", average),
-            _ => (),
+            PaymentMethod::Efectivo => {
+                println!("Tiempo promedio con Pago Efectivo = {} mins.", average)
+            }
+            PaymentMethod::Tarjeta => {
+                println!("Tiempo promedio con Pago Tarjeta  = {} mins.", average)
+            }
+            PaymentMethod::CopecApp => {
+                println!("Tiempo promedio con Pago CopecApp = {} mins.", average)
+            }
+        }
+    }
+}
+
+// Promedios de espera, atención (carga + pago) y tiempo total de los clientes
+// atendidos, y la dispersión de la espera.
+pub fn time_breakdown(customer_data: &CustomerData) {
+    let mut wait_sum = 0.0;
+    let mut service_sum = 0.0;
+    let mut total_sum = 0.0;
+    let mut count = 0.0;
+    let mut blocked_sum = 0.0;
+    let mut waits = Vec::new();
+
+    for record in customer_data.values().filter(|record| record.served) {
+        if let (Some(wait), Some(service), Some(blocked), Some(total)) = (
+            record.wait_time(),
+            record.service_time(),
+            record.blocked_time(),
+            record.total_time(),
+        ) {
+            wait_sum += wait;
+            service_sum += service;
+            blocked_sum += blocked;
+            total_sum += total;
+            count += 1.0;
+            waits.push(wait);
+        }
+    }
+
+    if count > 0.0 {
+        println!(
+            "Tiempo promedio en cola = {:.4} mins, en atención = {:.4} mins, total = {:.4} mins.",
+            wait_sum / count,
+            service_sum / count,
+            total_sum / count
+        );
+        if blocked_sum > 0.0 {
+            println!(
+                "Tiempo promedio bloqueado sin poder salir = {:.4} mins.",
+                blocked_sum / count
+            );
+        }
+    }
+    if waits.len() > 1 {
+        let mean = wait_sum / count;
+        let variance = waits.iter().map(|wait| (wait - mean).powi(2)).sum::<f64>() / (count - 1.0);
+        println!(
+            "Varianza de la espera = {:.4} mins² (desv. est. {:.4} mins).",
+            variance,
+            variance.sqrt()
+        );
+    }
+}
+
+// Clientes que se fueron sin hacer fila (ventas perdidas), por método de pago,
+// por hora del día y, con más de una categoría, por vehículo, sobre el total
+// de llegadas.
+pub fn lost_sales(customer_data: &CustomerData) {
+    let mut by_method: BTreeMap<PaymentMethod, (usize, usize)> = BTreeMap::new();
+    let mut by_hour: BTreeMap<u32, (usize, usize)> = BTreeMap::new();
+    let mut by_vehicle: BTreeMap<Vehicle, (usize, usize)> = BTreeMap::new();
+
+    for record in customer_data.values() {
+        let balked = usize::from(record.balked);
+        let method = by_method.entry(record.payment_method).or_insert((0, 0));
+        method.0 += 1;
+        method.1 += balked;
+        let vehicle = by_vehicle.entry(record.vehicle).or_insert((0, 0));
+        vehicle.0 += 1;
+        vehicle.1 += balked;
+        let hour = by_hour.entry(hour_of_day(record.arrival)).or_insert((0, 0));
+        hour.0 += 1;
+        hour.1 += balked;
+    }
+
+    if by_method.values().all(|&(_, balked)| balked == 0) {
+        return;
+    }
+
+    println!("Ventas perdidas (balking):");
+    println!(
+        "{:<9} | {:>8} | {:>8} | {:>7}",
+        "MÉTODO", "LLEGADAS", "PERDIDAS", "%"
+    );
+    for (payment_method, (arrivals, balked)) in by_method.iter() {
+        println!(
+            "{:<9} | {:>8} | {:>8} | {:>6.1}%",
+            format!("{:?}", payment_method),
+            arrivals,
+            balked,
+            *balked as f64 / *arrivals as f64 * 100.0
+        );
+    }
+    println!(
+        "{:<9} | {:>8} | {:>8} | {:>7}",
+        "HORA", "LLEGADAS", "PERDIDAS", "%"
+    );
+    for (hour, (arrivals, balked)) in by_hour.iter() {
+        println!(
+            "{:<9} | {:>8} | {:>8} | {:>6.1}%",
+            format!("{:02}:00", hour),
+            arrivals,
+            balked,
+            *balked as f64 / *arrivals as f64 * 100.0
+        );
+    }
+    if by_vehicle.len() > 1 {
+        println!(
+            "{:<9} | {:>8} | {:>8} | {:>7}",
+            "VEHÍCULO", "LLEGADAS", "PERDIDAS", "%"
+        );
+        for (vehicle, (arrivals, balked)) in by_vehicle.iter() {
+            println!(
+                "{:<9} | {:>8} | {:>8} | {:>6.1}%",
+                vehicle.to_string(),
+                arrivals,
+                balked,
+                *balked as f64 / *arrivals as f64 * 100.0
+            );
+        }
+    }
+}
+
+// Abandonos de cola (reneging) por método de pago y, con más de una
+// categoría, por vehículo, con la distribución del tiempo que esperaron antes
+// de abandonar.
+pub fn abandonments(customer_data: &CustomerData) {
+    let mut arrivals: BTreeMap<PaymentMethod, usize> = BTreeMap::new();
+    let mut waits: BTreeMap<PaymentMethod, Vec<f64>> = BTreeMap::new();
+    let mut vehicle_arrivals: BTreeMap<Vehicle, usize> = BTreeMap::new();
+    let mut vehicle_waits: BTreeMap<Vehicle, Vec<f64>> = BTreeMap::new();
+
+    for record in customer_data.values() {
+        *arrivals.entry(record.payment_method).or_insert(0) += 1;
+        *vehicle_arrivals.entry(record.vehicle).or_insert(0) += 1;
+        if let (true, Some(wait)) = (record.reneged, record.total_time()) {
+            waits.entry(record.payment_method).or_default().push(wait);
+            vehicle_waits.entry(record.vehicle).or_default().push(wait);
+        }
+    }
+
+    if waits.is_empty() {
+        return;
+    }
+
+    let mut all_waits: Vec<f64> = waits.values().flatten().copied().collect();
+    let total_arrivals: usize = arrivals.values().sum();
+
+    println!("Abandonos de cola (reneging), minutos esperados antes de abandonar:");
+    println!(
+        "{:<9} | {:>9} | {:>6} | {:>8} | {:>7} | {:>7} | {:>7}",
+        "MÉTODO", "ABANDONOS", "%", "PROMEDIO", "P50", "P90", "MÁXIMO"
+    );
+    let print_row = |name: String, waits: &mut Vec<f64>, arrivals: usize| {
+        waits.sort_by(|a, b| a.total_cmp(b));
+        let quantile = |q: f64| waits[((waits.len() - 1) as f64 * q).round() as usize];
+        println!(
+            "{:<9} | {:>9} | {:>5.1}% | {:>8.3} | {:>7.3} | {:>7.3} | {:>7.3}",
+            name,
+            waits.len(),
+            waits.len() as f64 / arrivals as f64 * 100.0,
+            waits.iter().sum::<f64>() / waits.len() as f64,
+            quantile(0.5),
+            quantile(0.9),
+            waits[waits.len() - 1]
+        );
+    };
+    for (payment_method, method_waits) in waits.iter_mut() {
+        print_row(
+            format!("{:?}", payment_method),
+            method_waits,
+            arrivals[payment_method],
+        );
+    }
+    if vehicle_arrivals.len() > 1 {
+        println!(
+            "{:<9} | {:>9} | {:>6} | {:>8} | {:>7} | {:>7} | {:>7}",
+            "VEHÍCULO", "ABANDONOS", "%", "PROMEDIO", "P50", "P90", "MÁXIMO"
+        );
+        for (vehicle, vehicle_waits) in vehicle_waits.iter_mut() {
+            print_row(
+                vehicle.to_string(),
+                vehicle_waits,
+                vehicle_arrivals[vehicle],
+            );
+        }
+    }
+    print_row(String::from("Total"), &mut all_waits, total_arrivals);
+}
+
+// Espera de los clientes atendidos según el combustible que cargan, junto con
+// los que se fueron sin cargar: por balking o abandono, por falta de stock y
+// porque ningún surtidor los podía atender.
+pub fn fuel_waits(customer_data: &CustomerData) {
+    let mut waits: BTreeMap<Fuel, Vec<f64>> = BTreeMap::new();
+    let mut lost: BTreeMap<Fuel, usize> = BTreeMap::new();
+    let mut stocked_out: BTreeMap<Fuel, usize> = BTreeMap::new();
+    let mut unserved: BTreeMap<Fuel, usize> = BTreeMap::new();
+
+    for record in customer_data.values() {
+        if record.served {
+            if let Some(wait) = record.wait_time() {
+                waits.entry(record.fuel).or_default().push(wait);
+            }
+        } else if record.stocked_out {
+            *stocked_out.entry(record.fuel).or_insert(0) += 1;
+        } else if record.unserved {
+            *unserved.entry(record.fuel).or_insert(0) += 1;
+        } else if record.balked || record.reneged {
+            *lost.entry(record.fuel).or_insert(0) += 1;
+        }
+    }
+
+    println!(
+        "{:<11} | {:>9} | {:>13} | {:>12} | {:>8} | {:>9} | {:>12}",
+        "COMBUSTIBLE",
+        "ATENDIDOS",
+        "ESPERA PROM.",
+        "ESPERA MÁX.",
+        "PERDIDOS",
+        "SIN STOCK",
+        "SIN SURTIDOR"
+    );
+    for fuel in Fuel::ALL {
+        let fuel_waits = waits.get(&fuel).map(Vec::as_slice).unwrap_or(&[]);
+        let lost = lost.get(&fuel).copied().unwrap_or(0);
+        let stocked_out = stocked_out.get(&fuel).copied().unwrap_or(0);
+        let unserved = unserved.get(&fuel).copied().unwrap_or(0);
+        if fuel_waits.is_empty() && lost == 0 && stocked_out == 0 && unserved == 0 {
+            continue;
+        }
+        let average = if fuel_waits.is_empty() {
+            0.0
+        } else {
+            fuel_waits.iter().sum::<f64>() / fuel_waits.len() as f64
+        };
+        println!(
+            "{:<11} | {:>9} | {:>13.3} | {:>12.3} | {:>8} | {:>9} | {:>12}",
+            fuel.to_string(),
+            fuel_waits.len(),
+            average,
+            fuel_waits.iter().copied().fold(0.0, f64::max),
+            lost,
+            stocked_out,
+            unserved
+        );
+    }
+}
+
+// Litros despachados y tiempo medio de carga por surtidor, cuando el
+// escenario modela los litros de cada cliente.
+pub fn dispensing(customer_data: &CustomerData) {
+    // surtidor -> (clientes, litros, minutos de carga)
+    let mut by_station: BTreeMap<usize, (usize, f64, f64)> = BTreeMap::new();
+
+    for record in customer_data.values().filter(|record| record.served) {
+        if let (Some(station), Some(litres), Some(refuel)) =
+            (record.station, record.litres, record.refuel_time())
+        {
+            let entry = by_station.entry(station).or_insert((0, 0.0, 0.0));
+            entry.0 += 1;
+            entry.1 += litres;
+            entry.2 += refuel;
+        }
+    }
+
+    if by_station.is_empty() {
+        return;
+    }
+
+    println!(
+        "{:<9} | {:>9} | {:>10} | {:>14} | {:>12}",
+        "SURTIDOR", "ATENDIDOS", "LITROS", "LITROS/CLIENTE", "CARGA PROM."
+    );
+    for (station, (count, litres, refuel)) in by_station.iter() {
+        println!(
+            "{:<9} | {:>9} | {:>10.1} | {:>14.2} | {:>12.3}",
+            station,
+            count,
+            litres,
+            litres / *count as f64,
+            refuel / *count as f64
+        );
+    }
+}
+
+// Espera de los clientes que llegaron durante cada corte de servicio,
+// comparada con la de quienes llegaron con todos los surtidores en servicio.
+pub fn outage_impact(customer_data: &CustomerData, outages: &[Outage]) {
+    if outages.is_empty() {
+        return;
+    }
+    let average_wait = |waits: &[f64]| {
+        if waits.is_empty() {
+            0.0
+        } else {
+            waits.iter().sum::<f64>() / waits.len() as f64
+        }
+    };
+
+    println!("Cortes de servicio:");
+    println!(
+        "{:<9} | {:<10} | {:>9} | {:>9} | {:>11} | {:>9} | {:>12}",
+        "SURTIDOR", "CAUSA", "INICIO", "DURACIÓN", "REDIRIGIDOS", "LLEGADAS", "ESPERA PROM."
+    );
+    for outage in outages {
+        let waits: Vec<f64> = customer_data
+            .values()
+            .filter(|record| record.served && outage.covers(record.arrival))
+            .filter_map(|record| record.wait_time())
+            .collect();
+        let duration = match outage.end {
+            Some(end) => format!("{:.1}", end - outage.start),
+            None => String::from("abierto"),
+        };
+        println!(
+            "{:<9} | {:<10} | {:>9.1} | {:>9} | {:>11} | {:>9} | {:>12.3}",
+            outage.station,
+            outage.cause.to_string(),
+            outage.start,
+            duration,
+            outage.redirected,
+            waits.len(),
+            average_wait(&waits)
+        );
+    }
+    let normal_waits: Vec<f64> = customer_data
+        .values()
+        .filter(|record| {
+            record.served && !outages.iter().any(|outage| outage.covers(record.arrival))
+        })
+        .filter_map(|record| record.wait_time())
+        .collect();
+    println!(
+        "(INICIO y DURACIÓN en minutos.) Espera promedio sin cortes = {:.3} mins.",
+        average_wait(&normal_waits)
+    );
+}
+
+// Por hora del día: bomberos de turno en promedio, su utilización y la espera
+// de los clientes que llegaron en esa hora, total y por un bombero.
+pub fn staffing(customer_data: &CustomerData, attendants: &AttendantStats) {
+    // hora -> (atendidos, espera, espera por bombero)
+    let mut by_hour: BTreeMap<usize, (usize, f64, f64)> = BTreeMap::new();
+    for record in customer_data.values().filter(|record| record.served) {
+        if let Some(wait) = record.wait_time() {
+            let hour = by_hour
+                .entry(hour_of_day(record.arrival) as usize)
+                .or_insert((0, 0.0, 0.0));
+            hour.0 += 1;
+            hour.1 += wait;
+            hour.2 += record.attendant_wait;
+        }
+    }
+
+    println!("Bomberos por hora:");
+    println!(
+        "{:<4} | {:>8} | {:>11} | {:>9} | {:>12} | {:>14}",
+        "HORA", "BOMBEROS", "UTILIZACIÓN", "ATENDIDOS", "ESPERA PROM.", "ESPERA BOMBERO"
+    );
+    for hour in 0..24 {
+        if attendants.elapsed[hour] <= 0.0 {
+            continue;
+        }
+        let utilization = if attendants.on_duty_minutes[hour] > 0.0 {
+            attendants.busy_minutes[hour] / attendants.on_duty_minutes[hour] * 100.0
+        } else {
+            0.0
+        };
+        let (served, wait, attendant_wait) = by_hour.get(&hour).copied().unwrap_or_default();
+        let per_customer = |minutes: f64| {
+            if served > 0 {
+                minutes / served as f64
+            } else {
+                0.0
+            }
+        };
+        println!(
+            "{:<4} | {:>8.2} | {:>10.1}% | {:>9} | {:>12.3} | {:>14.3}",
+            hour,
+            attendants.on_duty_minutes[hour] / attendants.elapsed[hour],
+            utilization,
+            served,
+            per_customer(wait),
+            per_customer(attendant_wait)
+        );
+    }
+}
+
+// Por medio de pago: cuántos pagaron en la caja de la tienda, su fila allí y
+// el tiempo total de pago, que incluye la caminata y la fila.
+pub fn counter_payments(customer_data: &CustomerData) {
+    // medio de pago -> (atendidos, en caja, espera en caja, pago)
+    let mut by_method: BTreeMap<PaymentMethod, (usize, usize, f64, f64)> = BTreeMap::new();
+    for record in customer_data.values().filter(|record| record.served) {
+        if let Some(payment) = record.payment_time() {
+            let method = by_method
+                .entry(record.payment_method)
+                .or_insert((0, 0, 0.0, 0.0));
+            method.0 += 1;
+            method.3 += payment;
+            if record.paid_at_counter {
+                method.1 += 1;
+                method.2 += record.counter_wait;
+            }
         }
     }
+    if by_method.values().all(|method| method.1 == 0) {
+        return;
+    }
+
+    println!("Pagos en caja:");
+    println!(
+        "{:<11} | {:>9} | {:>7} | {:>11} | {:>10}",
+        "MEDIO", "ATENDIDOS", "EN CAJA", "ESPERA CAJA", "PAGO PROM."
+    );
+    for (payment_method, (served, at_counter, counter_wait, payment)) in by_method.iter() {
+        let counter_wait = if *at_counter > 0 {
+            counter_wait / *at_counter as f64
+        } else {
+            0.0
+        };
+        println!(
+            "{:<11} | {:>9} | {:>7} | {:>11.3} | {:>10.3}",
+            payment_method.to_string(),
+            served,
+            at_counter,
+            counter_wait,
+            payment / *served as f64
+        );
+    }
+}
+
+// Resultados por categoría de vehículo: llegadas, atendidos, perdidos (balking,
+// abandono o falta de stock), espera, tiempo total y litros por cliente.
+pub fn vehicle_breakdown(customer_data: &CustomerData) {
+    let vehicles: Vec<Vehicle> = Vehicle::ALL
+        .iter()
+        .copied()
+        .filter(|&vehicle| {
+            customer_data
+                .values()
+                .any(|record| record.vehicle == vehicle)
+        })
+        .collect();
+    // Con una sola categoría la tabla repite los totales.
+    if vehicles.len() < 2 {
+        return;
+    }
+
+    println!(
+        "{:<9} | {:>8} | {:>9} | {:>8} | {:>12} | {:>11} | {:>14}",
+        "VEHÍCULO",
+        "LLEGADAS",
+        "ATENDIDOS",
+        "PERDIDOS",
+        "ESPERA PROM.",
+        "TOTAL PROM.",
+        "LITROS/CLIENTE"
+    );
+    for vehicle in vehicles {
+        let records: Vec<_> = customer_data
+            .values()
+            .filter(|record| record.vehicle == vehicle)
+            .collect();
+        let served: Vec<_> = records.iter().filter(|record| record.served).collect();
+        let lost = records
+            .iter()
+            .filter(|record| {
+                record.balked || record.reneged || record.stocked_out || record.unserved
+            })
+            .count();
+        let average = |values: Vec<f64>| {
+            if values.is_empty() {
+                f64::NAN
+            } else {
+                values.iter().sum::<f64>() / values.len() as f64
+            }
+        };
+        println!(
+            "{:<9} | {:>8} | {:>9} | {:>8} | {:>12.3} | {:>11.3} | {:>14.2}",
+            vehicle.to_string(),
+            records.len(),
+            served.len(),
+            lost,
+            average(
+                served
+                    .iter()
+                    .filter_map(|record| record.wait_time())
+                    .collect()
+            ),
+            average(
+                served
+                    .iter()
+                    .filter_map(|record| record.total_time())
+                    .collect()
+            ),
+            average(served.iter().filter_map(|record| record.litres).collect())
+        );
+    }
 }
 
-pub fn lambda_avg(customer_data: &mut HashMap<u64, (u8, f64, f64, f64, u8)>) {}
+pub fn lambda_avg(_customer_data: &CustomerData) {}
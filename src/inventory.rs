@@ -0,0 +1,229 @@
+use crate::customer::Fuel;
+use crate::scenario::{InventoryScenario, Resupply, TankParams};
+use crate::stats::TimeWeighted;
+
+// Estanque subterráneo de un combustible, con sus estadísticas.
+#[derive(Clone, Debug)]
+pub struct Tank {
+    pub capacity: f64,
+    pub level: f64,
+    // Hay un camión pedido que todavía no termina de descargar.
+    pub on_order: bool,
+    pub unloading: bool,
+    pub stock: TimeWeighted,
+    // Indicadores 0/1: sin combustible, y cerrado (sin combustible o descargando).
+    pub stock_out: TimeWeighted,
+    pub closed: TimeWeighted,
+    pub deliveries: u64,
+    pub delivered: f64,
+    pub dispensed: f64,
+    // Litros que no se vendieron: clientes que se fueron y cargas incompletas.
+    pub lost_litres: f64,
+    pub lost_customers: u64,
+}
+
+impl Tank {
+    fn new(params: TankParams) -> Self {
+        let mut tank = Tank {
+            capacity: params.capacity,
+            level: params.initial.unwrap_or(params.capacity),
+            on_order: false,
+            unloading: false,
+            stock: TimeWeighted::new(0.0),
+            stock_out: TimeWeighted::new(0.0),
+            closed: TimeWeighted::new(0.0),
+            deliveries: 0,
+            delivered: 0.0,
+            dispensed: 0.0,
+            lost_litres: 0.0,
+            lost_customers: 0,
+        };
+        tank.record(0.0);
+        tank
+    }
+
+    pub fn stocked_out(&self) -> bool {
+        self.level <= 0.0
+    }
+
+    pub fn open(&self) -> bool {
+        !self.unloading && !self.stocked_out()
+    }
+
+    fn record(&mut self, time: f64) {
+        let stock_out = if self.stocked_out() { 1.0 } else { 0.0 };
+        let closed = if self.open() { 0.0 } else { 1.0 };
+        self.stock.update(time, self.level);
+        self.stock_out.update(time, stock_out);
+        self.closed.update(time, closed);
+    }
+}
+
+// Estanques de la estación, indexados por `Fuel::index`. None si el
+// combustible no se controla.
+#[derive(Clone, Debug)]
+pub struct Tanks {
+    tanks: Vec<Option<Tank>>,
+}
+
+impl Tanks {
+    pub fn new(scenario: &InventoryScenario) -> Self {
+        Tanks {
+            tanks: Fuel::ALL
+                .iter()
+                .map(|&fuel| scenario.tanks.get(fuel).map(Tank::new))
+                .collect(),
+        }
+    }
+
+    pub fn get(&self, fuel: Fuel) -> Option<&Tank> {
+        self.tanks[fuel.index()].as_ref()
+    }
+
+    fn get_mut(&mut self, fuel: Fuel) -> Option<&mut Tank> {
+        self.tanks[fuel.index()].as_mut()
+    }
+
+    // Combustibles con estanque.
+    pub fn tracked(&self) -> impl Iterator<Item = (Fuel, &Tank)> {
+        Fuel::ALL
+            .iter()
+            .zip(&self.tanks)
+            .filter_map(|(&fuel, tank)| tank.as_ref().map(|tank| (fuel, tank)))
+    }
+
+    // Se puede empezar a cargar el combustible.
+    pub fn open(&self, fuel: Fuel) -> bool {
+        match self.get(fuel) {
+            Some(tank) => tank.open(),
+            None => true,
+        }
+    }
+
+    pub fn stocked_out(&self, fuel: Fuel) -> bool {
+        match self.get(fuel) {
+            Some(tank) => tank.stocked_out(),
+            None => false,
+        }
+    }
+
+    // Descuenta una carga terminada y devuelve los litros despachados, menos
+    // que `litres` si el estanque se vació durante la carga.
+    pub fn draw(&mut self, fuel: Fuel, litres: f64, time: f64) -> f64 {
+        let tank = match self.get_mut(fuel) {
+            Some(tank) => tank,
+            None => return litres,
+        };
+        let dispensed = litres.min(tank.level);
+        tank.level -= dispensed;
+        tank.dispensed += dispensed;
+        tank.lost_litres += litres - dispensed;
+        tank.record(time);
+        dispensed
+    }
+
+    // Un cliente se fue porque su combustible estaba agotado.
+    pub fn lose(&mut self, fuel: Fuel, litres: f64) {
+        if let Some(tank) = self.get_mut(fuel) {
+            tank.lost_customers += 1;
+            tank.lost_litres += litres;
+        }
+    }
+
+    // Con la política (s, Q), marca el pedido si corresponde hacerlo ahora.
+    pub fn place_order(&mut self, fuel: Fuel, resupply: &Resupply) -> bool {
+        let reorder_level = match resupply {
+            Resupply::ReorderPoint { reorder_level, .. } => *reorder_level,
+            Resupply::None | Resupply::Scheduled { .. } => return false,
+        };
+        match self.get_mut(fuel) {
+            Some(tank) if !tank.on_order && tank.level <= reorder_level => {
+                tank.on_order = true;
+                true
+            }
+            _ => false,
+        }
+    }
+
+    pub fn start_unloading(&mut self, fuel: Fuel, time: f64) {
+        if let Some(tank) = self.get_mut(fuel) {
+            tank.unloading = true;
+            tank.record(time);
+        }
+    }
+
+    // Termina la descarga: con (s, Q) llegan los litros pedidos y con camión
+    // programado se llena el estanque, sin superar la capacidad.
+    pub fn finish_unloading(&mut self, fuel: Fuel, resupply: &Resupply, time: f64) {
+        if let Some(tank) = self.get_mut(fuel) {
+            let quantity = match resupply {
+                Resupply::ReorderPoint { order_quantity, .. } => *order_quantity,
+                Resupply::Scheduled { .. } => tank.capacity,
+                Resupply::None => 0.0,
+            };
+            let delivered = quantity.min(tank.capacity - tank.level);
+            tank.level += delivered;
+            tank.delivered += delivered;
+            tank.deliveries += 1;
+            tank.on_order = false;
+            tank.unloading = false;
+            tank.record(time);
+        }
+    }
+
+    // Nivel, quiebre y cierre de cada estanque se integran desde `time`;
+    // entregas, despachos y pérdidas vuelven a cero.
+    pub fn reset(&mut self, time: f64) {
+        for tank in self.tanks.iter_mut().flatten() {
+            tank.stock.restart(time);
+            tank.stock_out.restart(time);
+            tank.closed.restart(time);
+            tank.deliveries = 0;
+            tank.delivered = 0.0;
+            tank.dispensed = 0.0;
+            tank.lost_litres = 0.0;
+            tank.lost_customers = 0;
+        }
+    }
+
+    pub fn stock_out_minutes(&self, end_time: f64) -> f64 {
+        self.tracked()
+            .map(|(_, tank)| tank.stock_out.total(end_time))
+            .sum()
+    }
+
+    pub fn lost_litres(&self) -> f64 {
+        self.tracked().map(|(_, tank)| tank.lost_litres).sum()
+    }
+
+    pub fn print(&self, end_time: f64) {
+        if self.tracked().next().is_none() {
+            return;
+        }
+        println!(
+            "{:<11} | {:>8} | {:>11} | {:>12} | {:>10} | {:>10} | {:>12} | {:>9}",
+            "ESTANQUE",
+            "ENTREGAS",
+            "RECIBIDOS",
+            "NIVEL PROM.",
+            "SIN STOCK",
+            "CERRADO",
+            "L. PERDIDOS",
+            "PERDIDOS"
+        );
+        for (fuel, tank) in self.tracked() {
+            println!(
+                "{:<11} | {:>8} | {:>11.0} | {:>12.0} | {:>10.1} | {:>10.1} | {:>12.0} | {:>9}",
+                fuel.to_string(),
+                tank.deliveries,
+                tank.delivered,
+                tank.stock.mean(end_time),
+                tank.stock_out.total(end_time),
+                tank.closed.total(end_time),
+                tank.lost_litres,
+                tank.lost_customers
+            );
+        }
+        println!("(SIN STOCK y CERRADO en minutos; CERRADO incluye las descargas.)");
+    }
+}
@@ -0,0 +1,33 @@
+// Simulación de eventos discretos de una estación de servicio. El binario
+// arma la línea de comandos sobre esta biblioteca; desde código se puede
+// correr `simulation` con un `Scenario` propio, por ejemplo con una política
+// de elección de cola a medida (`QueueSelection::custom`).
+#[macro_use]
+extern crate timeit;
+
+pub mod attendants;
+pub mod batchmeans;
+pub mod counter;
+pub mod customer;
+pub mod event;
+pub mod eventqueue;
+pub mod graphs;
+pub mod inventory;
+pub mod replication;
+pub mod rng;
+pub mod routines;
+pub mod scenario;
+pub mod selection;
+pub mod simulation;
+pub mod state;
+pub mod stats;
+pub mod warmup;
+pub use customer::{Customer, CustomerData, CustomerRecord, Fuel, PaymentMethod};
+pub use event::{Event, EventKind};
+pub use eventqueue::EventQueue;
+pub use rng::SimRng;
+pub use scenario::Scenario;
+pub use selection::{QueueSelection, QueueSelectionPolicy, SelectionContext};
+pub use simulation::{simulation, RunLength, SimulationSummary};
+pub use state::SimState;
+pub use stats::StationStats;
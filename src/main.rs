@@ -1,116 +1,240 @@
-#[macro_use]
-extern crate timeit;
-
-mod customer;
-mod event;
-mod eventqueue;
-mod graphs;
-mod routines;
-use customer::{Customer, PaymentMethod};
-use event::Event;
-use eventqueue::EventQueue;
-use graphs::*;
-use routines::*;
+use kitten_sim::batchmeans::print_batch_means;
+use kitten_sim::graphs::*;
+use kitten_sim::replication::{
+    default_threads, print_estimates, run_replications, run_station_sweep, summarize,
+};
+use kitten_sim::warmup::{simulation_with_warmup, Warmup};
+use kitten_sim::{CustomerData, RunLength, Scenario};
 
 //use ordered_float::OrderedFloat;
-use std::{collections::HashMap, env};
-
-fn simulation(
-    _steps: i32,
-    customer_data: &mut HashMap<u64, (u8, f64, f64, f64, u8)>,
-    fuel_station_length: usize,
-) {
-    let mut event_queue = EventQueue::new();
-    let mut sim_time = 0.0;
-    let mut customer_count = 0;
-
-    let mut fuel_stations = vec![0; fuel_station_length];
-    let mut customer_queues: Vec<Vec<Customer>> = vec![Vec::new(); fuel_station_length];
-
-    let initial_event = Event::new(0, Customer::new(0, 0.0), 0.0, None);
-    event_queue.add(initial_event);
-    // println!(
-    //     "{:<8} | {:<10} | {:<8} | {:<4} | {:<10}",
-    //     "TIEMPO", "EVENTO", "CLIENTE", "COLA", "ESTADO COLA"
-    // );
-
-    let sec = timeit_loops!(1, {
-        for _i in 0.._steps {
-            match time_routine(&mut event_queue, &mut sim_time) {
-                Some(mut e) => {
-                    match e.id {
-                        0 => {
-                            arrive_routine(
-                                &mut event_queue,
-                                &mut sim_time,
-                                &mut e,
-                                &mut customer_count,
-                                customer_data,
-                            );
-                        }
-                        1 => {
-                            queue_routine(
-                                &mut event_queue,
-                                &mut sim_time,
-                                &mut e,
-                                &mut fuel_stations,
-                                &mut customer_queues,
-                                customer_data,
-                            );
-                        }
-                        2 => {
-                            refuel_routine(&mut event_queue, &mut sim_time, &mut e);
-                        }
-                        3 => {
-                            payment_routine(&mut event_queue, &mut sim_time, &mut e);
-                        }
-                        4 => {
-                            departure_routine(
-                                &mut event_queue,
-                                &mut sim_time,
-                                &mut e,
-                                &mut fuel_stations,
-                                &mut customer_queues,
-                                customer_data,
-                            );
-                        }
-                        _ => {
-                            todo!();
+use rand::Rng;
+use std::env;
+
+struct Args {
+    run_length: RunLength,
+    seed: Option<u64>,
+    scenario: Option<String>,
+    replications: Option<u64>,
+    confidence: f64,
+    threads: usize,
+    sweep_stations: Option<Vec<usize>>,
+    warmup: Warmup,
+    batches: Option<usize>,
+}
+
+// Opciones que esperan un valor a continuación.
+const VALUE_FLAGS: [&str; 12] = [
+    "--seed",
+    "--scenario",
+    "--minutes",
+    "--days",
+    "--customers",
+    "--events",
+    "--replications",
+    "--confidence",
+    "--threads",
+    "--sweep-stations",
+    "--warmup",
+    "--batches",
+];
+
+// Uso: kitten_sim [pasos] [--seed N] [--scenario archivo.toml]
+//                 [--minutes N | --days N | --customers N | --events N]
+//                 [--replications N] [--confidence C] [--threads N]
+//                 [--sweep-stations N,M,...] [--warmup minutos|auto]
+//                 [--batches N]
+fn parse_args() -> Args {
+    let mut args = Args {
+        run_length: RunLength::Events(100),
+        seed: None,
+        scenario: None,
+        replications: None,
+        confidence: 0.95,
+        threads: default_threads(),
+        sweep_stations: None,
+        warmup: Warmup::None,
+        batches: None,
+    };
+    let mut iter = env::args().skip(1);
+    while let Some(arg) = iter.next() {
+        let value = if VALUE_FLAGS.contains(&arg.as_str()) {
+            iter.next()
+        } else {
+            None
+        };
+        let parsed = match (arg.as_str(), value.as_deref()) {
+            ("--seed", Some(v)) => v.parse().map(|seed| args.seed = Some(seed)).is_ok(),
+            ("--scenario", Some(v)) => {
+                args.scenario = Some(v.to_string());
+                true
+            }
+            ("--minutes", Some(v)) => v
+                .parse()
+                .map(|minutes| args.run_length = RunLength::Minutes(minutes))
+                .is_ok(),
+            ("--days", Some(v)) => v
+                .parse()
+                .map(|days| args.run_length = RunLength::Days(days))
+                .is_ok(),
+            ("--customers", Some(v)) => v
+                .parse()
+                .map(|customers| args.run_length = RunLength::Customers(customers))
+                .is_ok(),
+            ("--events", Some(v)) => v
+                .parse()
+                .map(|events| args.run_length = RunLength::Events(events))
+                .is_ok(),
+            ("--replications", Some(v)) => v
+                .parse()
+                .map(|replications| args.replications = Some(replications))
+                .is_ok(),
+            ("--confidence", Some(v)) => match v.parse::<f64>() {
+                Ok(confidence) if confidence > 0.0 && confidence < 1.0 => {
+                    args.confidence = confidence;
+                    true
+                }
+                _ => false,
+            },
+            ("--threads", Some(v)) => match v.parse::<usize>() {
+                Ok(threads) if threads > 0 => {
+                    args.threads = threads;
+                    true
+                }
+                _ => false,
+            },
+            ("--sweep-stations", Some(v)) => {
+                match v.split(',').map(|n| n.trim().parse::<usize>()).collect() {
+                    Ok(counts) => {
+                        let counts: Vec<usize> = counts;
+                        let valid = !counts.is_empty() && counts.iter().all(|&n| n > 0);
+                        if valid {
+                            args.sweep_stations = Some(counts);
                         }
+                        valid
                     }
-                    /*println!(
-                        "{:<8} | {}",
-                        e.pretty_print(),
-                        format_customer_queues(&customer_queues)
-                    );*/
-                }
-                None => {
-                    todo!();
+                    Err(_) => false,
                 }
             }
+            ("--warmup", Some("auto")) => {
+                args.warmup = Warmup::Auto;
+                true
+            }
+            ("--warmup", Some(v)) => match v.parse::<f64>() {
+                Ok(minutes) if minutes >= 0.0 => {
+                    args.warmup = Warmup::Fixed(minutes);
+                    true
+                }
+                _ => false,
+            },
+            ("--batches", Some(v)) => match v.parse::<usize>() {
+                Ok(batches) if batches >= 2 => {
+                    args.batches = Some(batches);
+                    true
+                }
+                _ => false,
+            },
+            // Compatibilidad: un número suelto es la cantidad de eventos.
+            (_, None) => arg
+                .parse()
+                .map(|events| args.run_length = RunLength::Events(events))
+                .is_ok(),
+            _ => false,
+        };
+        if !parsed {
+            eprintln!(
+                "Argumento inválido: {} {}, se ignora.",
+                arg,
+                value.unwrap_or_default()
+            );
+        }
+    }
+    // Un calentamiento que llega al horizonte descartaría la corrida completa.
+    if let (Warmup::Fixed(minutes), Some(horizon)) = (args.warmup, args.run_length.horizon()) {
+        if minutes >= horizon {
+            eprintln!(
+                "Argumento inválido: --warmup {} no termina antes del horizonte de {} mins, se ignora.",
+                minutes, horizon
+            );
+            args.warmup = Warmup::None;
         }
-    });
-    println!("Simulación terminada en {} segs.", sec);
+    }
+    args
 }
 
 fn main() {
-    let arg = env::args().nth(1);
-    let arg_steps = if let Some(arg) = arg {
-        if let Ok(arg_as_int) = arg.parse::<i32>() {
-            arg_as_int
-        } else {
-            100
-        }
-    } else {
-        100
+    let args = parse_args();
+    let run_length = args.run_length;
+    let seed = args.seed.unwrap_or_else(|| rand::thread_rng().gen());
+    println!("Semilla: {}", seed);
+
+    let scenario = match &args.scenario {
+        Some(path) => match Scenario::from_file(path) {
+            Ok(scenario) => scenario,
+            Err(err) => {
+                eprintln!("{}", err);
+                std::process::exit(1);
+            }
+        },
+        None => Scenario::default(),
     };
 
-    // HashMap con la información para hacer los gráficos.
-    // El format del HashMap es key: (int, float, float, float, int)
-    // Esto es ID: (Método de pago, tiempo de llegada (dentro de la simulación), tiempo esperando en cola, tiempo total de atención al momento de salir, si salió o no (1 o 0))
-    let mut customer_data: HashMap<u64, (u8, f64, f64, f64, u8)> = HashMap::new();
+    if let Some(station_counts) = &args.sweep_stations {
+        for &stations in station_counts {
+            if let Err(err) = scenario.validate_stations(stations) {
+                eprintln!("--sweep-stations: {}", err);
+                std::process::exit(1);
+            }
+        }
+        let replications = args.replications.unwrap_or(1);
+        let sweep = run_station_sweep(
+            &scenario,
+            station_counts,
+            run_length,
+            replications,
+            seed,
+            args.threads,
+            args.warmup,
+        );
+        for (stations, results) in sweep {
+            println!("{} réplicas con {} surtidores:", results.len(), stations);
+            print_estimates(&summarize(&results, args.confidence), args.confidence);
+        }
+        return;
+    }
 
-    simulation(arg_steps, &mut customer_data, 4);
+    if let Some(replications) = args.replications {
+        let results = run_replications(
+            &scenario,
+            scenario.stations,
+            run_length,
+            replications,
+            seed,
+            args.threads,
+            args.warmup,
+        );
+        println!(
+            "{} réplicas con {} surtidores:",
+            results.len(),
+            scenario.stations
+        );
+        print_estimates(&summarize(&results, args.confidence), args.confidence);
+        return;
+    }
+
+    // Registros de cada cliente para hacer los gráficos, ordenados por ID.
+    let mut customer_data = CustomerData::new();
+
+    let summary = simulation_with_warmup(
+        run_length,
+        &mut customer_data,
+        scenario.stations,
+        &scenario,
+        seed,
+        args.warmup,
+    );
+    // Por stderr, para que la salida de una semilla fija sea reproducible.
+    eprintln!("Simulación terminada en {} segs.", summary.wall_secs);
+    summary.print();
 
     /*if let Some(max) = customer_data.keys().max() {
         for i in 0..=*max {
@@ -120,18 +244,46 @@ fn main() {
         }
     }*/
 
-    payment_method_sensitivity(&mut customer_data);
+    payment_method_sensitivity(&customer_data);
+    time_breakdown(&customer_data);
+    fuel_waits(&customer_data);
+    dispensing(&customer_data);
+    vehicle_breakdown(&customer_data);
+    lost_sales(&customer_data);
+    abandonments(&customer_data);
+    outage_impact(&customer_data, &summary.stats.outages);
+    if let Some(attendants) = &summary.attendants {
+        staffing(&customer_data, attendants);
+    }
+    counter_payments(&customer_data);
+    if let Some(batches) = args.batches {
+        print_batch_means(&customer_data, batches, args.confidence);
+    }
 
-    let mut customer_data_5s: HashMap<u64, (u8, f64, f64, f64, u8)> = HashMap::new();
-    simulation(arg_steps, &mut customer_data_5s, 5);
+    let mut customer_data_5s = CustomerData::new();
+    // Misma semilla: ambas configuraciones ven la misma secuencia de llegadas.
+    let summary_5s = simulation_with_warmup(
+        run_length,
+        &mut customer_data_5s,
+        scenario.comparison_stations,
+        &scenario,
+        seed,
+        args.warmup,
+    );
+    eprintln!("Simulación terminada en {} segs.", summary_5s.wall_secs);
+    summary_5s.print();
 
     // Gráficos
     // Gráfico de tiempo promedio por método de pago
-    payment_method_avg_time(&mut customer_data);
+    payment_method_avg_time(&customer_data);
 
-    // tiempo promedio con 4 vs 5 estaciones
-    four_vs_five_stations(&mut customer_data, &mut customer_data_5s);
+    // tiempo promedio con 4 vs 5 estaciones (o las que indique el escenario)
+    four_vs_five_stations(
+        &customer_data,
+        &customer_data_5s,
+        (scenario.stations, scenario.comparison_stations),
+    );
 
     // promedio de tiempo de espera en cola por dia
-    queue_avg_waittime(&mut customer_data);
-}
\ No newline at end of file
+    queue_avg_waittime(&customer_data);
+}
@@ -0,0 +1,388 @@
+use crate::customer::{CustomerData, Fuel, Vehicle};
+use crate::rng::replication_seed;
+use crate::scenario::Scenario;
+use crate::simulation::{RunLength, SimulationSummary};
+use crate::stats::Estimate;
+use crate::warmup::{simulation_with_warmup, Warmup};
+use crate::PaymentMethod;
+
+use std::sync::atomic::{AtomicUsize, Ordering};
+use std::sync::Mutex;
+use std::thread;
+
+// Resultado de una réplica: el valor de cada indicador, en el orden de
+// `kpis`. Un indicador es None si la réplica no tuvo observaciones.
+pub struct ReplicationResult {
+    pub kpis: Vec<(&'static str, Option<f64>)>,
+}
+
+fn mean<I: Iterator<Item = f64>>(values: I) -> Option<f64> {
+    let (sum, count) = values.fold((0.0, 0usize), |(sum, count), x| (sum + x, count + 1));
+    if count > 0 {
+        Some(sum / count as f64)
+    } else {
+        None
+    }
+}
+
+// Varianza muestral de la espera de los clientes atendidos.
+fn wait_variance(customer_data: &CustomerData) -> Option<f64> {
+    let waits: Vec<f64> = customer_data
+        .values()
+        .filter(|record| record.served)
+        .filter_map(|record| record.wait_time())
+        .collect();
+    if waits.len() < 2 {
+        return None;
+    }
+    let mean = waits.iter().sum::<f64>() / waits.len() as f64;
+    Some(waits.iter().map(|wait| (wait - mean).powi(2)).sum::<f64>() / (waits.len() - 1) as f64)
+}
+
+// Indicadores de una corrida.
+pub fn kpis(
+    customer_data: &CustomerData,
+    summary: &SimulationSummary,
+) -> Vec<(&'static str, Option<f64>)> {
+    let served = || customer_data.values().filter(|record| record.served);
+    let tracked = summary.tanks.tracked().next().is_some();
+    let wait_by_fuel = |fuel: Fuel| {
+        mean(
+            served()
+                .filter(|record| record.fuel == fuel)
+                .filter_map(|record| record.wait_time()),
+        )
+    };
+    let total_time_by_method = |payment_method: PaymentMethod| {
+        mean(
+            served()
+                .filter(|record| record.payment_method == payment_method)
+                .filter_map(|record| record.total_time()),
+        )
+    };
+    let stations = summary.stats.pumps.len();
+    let total_time_by_vehicle = |vehicle: Vehicle| {
+        mean(
+            served()
+                .filter(|record| record.vehicle == vehicle)
+                .filter_map(|record| record.total_time()),
+        )
+    };
+
+    vec![
+        (
+            "Tiempo total Efectivo (min)",
+            total_time_by_method(PaymentMethod::Efectivo),
+        ),
+        (
+            "Tiempo total Tarjeta (min)",
+            total_time_by_method(PaymentMethod::Tarjeta),
+        ),
+        (
+            "Tiempo total CopecApp (min)",
+            total_time_by_method(PaymentMethod::CopecApp),
+        ),
+        (
+            "Tiempo total Auto (min)",
+            total_time_by_vehicle(Vehicle::Car),
+        ),
+        (
+            "Tiempo total Moto (min)",
+            total_time_by_vehicle(Vehicle::Motorcycle),
+        ),
+        (
+            "Tiempo total Furgón (min)",
+            total_time_by_vehicle(Vehicle::Van),
+        ),
+        (
+            "Tiempo total Camión (min)",
+            total_time_by_vehicle(Vehicle::Truck),
+        ),
+        (
+            "Tiempo total (min)",
+            mean(served().filter_map(|record| record.total_time())),
+        ),
+        (
+            "Espera en cola (min)",
+            mean(served().filter_map(|record| record.wait_time())),
+        ),
+        ("Varianza espera (min²)", wait_variance(customer_data)),
+        ("Espera 93 (min)", wait_by_fuel(Fuel::Gasolina93)),
+        ("Espera 95 (min)", wait_by_fuel(Fuel::Gasolina95)),
+        ("Espera 97 (min)", wait_by_fuel(Fuel::Gasolina97)),
+        ("Espera diésel (min)", wait_by_fuel(Fuel::Diesel)),
+        (
+            "Utilización surtidores",
+            mean((0..stations).map(|station| summary.stats.utilization(station))),
+        ),
+        (
+            "Disponibilidad surtidores",
+            mean((0..stations).map(|station| summary.stats.availability(station))),
+        ),
+        (
+            "Espera durante cortes (min)",
+            mean(
+                served()
+                    .filter(|record| {
+                        summary
+                            .stats
+                            .outages
+                            .iter()
+                            .any(|outage| outage.covers(record.arrival))
+                    })
+                    .filter_map(|record| record.wait_time()),
+            ),
+        ),
+        (
+            "Utilización bomberos",
+            summary
+                .attendants
+                .as_ref()
+                .and_then(|attendants| attendants.utilization()),
+        ),
+        (
+            "Espera por bombero (min)",
+            summary
+                .attendants
+                .as_ref()
+                .and(mean(served().map(|record| record.attendant_wait))),
+        ),
+        (
+            "Utilización caja",
+            summary
+                .counter
+                .as_ref()
+                .map(|counter| counter.utilization(summary.end_time)),
+        ),
+        (
+            "Espera en caja (min)",
+            summary.counter.as_ref().and(mean(
+                served()
+                    .filter(|record| record.paid_at_counter)
+                    .map(|record| record.counter_wait),
+            )),
+        ),
+        (
+            "Largo de cola promedio",
+            mean(
+                (0..summary.stats.queues.len()).map(|queue| summary.stats.avg_queue_length(queue)),
+            ),
+        ),
+        (
+            "Bloqueo de salida (min)",
+            mean(served().filter_map(|record| record.blocked_time())),
+        ),
+        ("Clientes atendidos", Some(summary.served as f64)),
+        (
+            "Litros despachados",
+            served().map(|record| record.litres).sum::<Option<f64>>(),
+        ),
+        (
+            "Minutos sin stock",
+            tracked.then(|| summary.tanks.stock_out_minutes(summary.end_time)),
+        ),
+        (
+            "Litros perdidos",
+            tracked.then(|| summary.tanks.lost_litres()),
+        ),
+        (
+            "Clientes perdidos (%)",
+            mean(customer_data.values().map(|record| {
+                if record.balked || record.stocked_out || record.unserved {
+                    100.0
+                } else {
+                    0.0
+                }
+            })),
+        ),
+        (
+            "Cambios de cola por cliente",
+            mean(customer_data.values().map(|record| record.jockeys as f64)),
+        ),
+        (
+            "Abandonos de cola (%)",
+            mean(
+                customer_data
+                    .values()
+                    .map(|record| if record.reneged { 100.0 } else { 0.0 }),
+            ),
+        ),
+        (
+            "Espera hasta abandonar (min)",
+            mean(
+                customer_data
+                    .values()
+                    .filter(|record| record.reneged)
+                    .filter_map(|record| record.total_time()),
+            ),
+        ),
+    ]
+}
+
+pub fn run_replication(
+    scenario: &Scenario,
+    stations: usize,
+    run_length: RunLength,
+    seed: u64,
+    warmup: Warmup,
+) -> ReplicationResult {
+    let mut customer_data = CustomerData::new();
+    let summary = simulation_with_warmup(
+        run_length,
+        &mut customer_data,
+        stations,
+        scenario,
+        seed,
+        warmup,
+    );
+    if let Some(err) = &summary.inconsistency {
+        eprintln!("Réplica con semilla {}: {}", seed, err);
+    }
+    ReplicationResult {
+        kpis: kpis(&customer_data, &summary),
+    }
+}
+
+// Ejecuta `jobs` trabajos independientes repartidos en `threads` hilos.
+// Cada resultado queda en la posición de su trabajo, así el orden de salida
+// no depende de cuántos hilos se usen ni de cuál termine primero.
+pub fn run_parallel<T, F>(jobs: usize, threads: usize, job: F) -> Vec<T>
+where
+    T: Send,
+    F: Fn(usize) -> T + Sync,
+{
+    let next_job = AtomicUsize::new(0);
+    let results: Mutex<Vec<Option<T>>> = Mutex::new((0..jobs).map(|_| None).collect());
+
+    thread::scope(|scope| {
+        for _ in 0..threads.clamp(1, jobs.max(1)) {
+            scope.spawn(|| loop {
+                let index = next_job.fetch_add(1, Ordering::Relaxed);
+                if index >= jobs {
+                    break;
+                }
+                let result = job(index);
+                results.lock().unwrap()[index] = Some(result);
+            });
+        }
+    });
+
+    results
+        .into_inner()
+        .unwrap()
+        .into_iter()
+        .map(|result| result.expect("todos los trabajos terminan"))
+        .collect()
+}
+
+// Hilos a usar por defecto: uno por núcleo disponible.
+pub fn default_threads() -> usize {
+    thread::available_parallelism()
+        .map(|threads| threads.get())
+        .unwrap_or(1)
+}
+
+// Corre `replications` réplicas independientes del escenario, cada una con
+// su propia semilla derivada de `seed`.
+pub fn run_replications(
+    scenario: &Scenario,
+    stations: usize,
+    run_length: RunLength,
+    replications: u64,
+    seed: u64,
+    threads: usize,
+    warmup: Warmup,
+) -> Vec<ReplicationResult> {
+    run_parallel(replications as usize, threads, |index| {
+        run_replication(
+            scenario,
+            stations,
+            run_length,
+            replication_seed(seed, index as u64),
+            warmup,
+        )
+    })
+}
+
+// Réplicas para cada cantidad de surtidores de `station_counts`. La réplica i
+// usa la misma semilla en todos los puntos (números aleatorios comunes), y
+// todos los pares (punto, réplica) se reparten juntos entre los hilos.
+pub fn run_station_sweep(
+    scenario: &Scenario,
+    station_counts: &[usize],
+    run_length: RunLength,
+    replications: u64,
+    seed: u64,
+    threads: usize,
+    warmup: Warmup,
+) -> Vec<(usize, Vec<ReplicationResult>)> {
+    let per_point = replications as usize;
+    let mut results = run_parallel(station_counts.len() * per_point, threads, |job| {
+        let stations = station_counts[job / per_point];
+        let index = (job % per_point) as u64;
+        run_replication(
+            scenario,
+            stations,
+            run_length,
+            replication_seed(seed, index),
+            warmup,
+        )
+    })
+    .into_iter();
+
+    station_counts
+        .iter()
+        .map(|&stations| (stations, results.by_ref().take(per_point).collect()))
+        .collect()
+}
+
+// Media, desviación estándar e intervalo de confianza de cada indicador
+// sobre las réplicas que lo observaron.
+pub fn summarize(
+    results: &[ReplicationResult],
+    confidence: f64,
+) -> Vec<(&'static str, Option<Estimate>)> {
+    let first = match results.first() {
+        Some(first) => first,
+        None => return Vec::new(),
+    };
+    first
+        .kpis
+        .iter()
+        .enumerate()
+        .map(|(index, &(name, _))| {
+            let sample: Vec<f64> = results
+                .iter()
+                .filter_map(|result| result.kpis[index].1)
+                .collect();
+            (name, Estimate::from_sample(&sample, confidence))
+        })
+        .collect()
+}
+
+pub fn print_estimates(estimates: &[(&'static str, Option<Estimate>)], confidence: f64) {
+    println!(
+        "{:<30} | {:>4} | {:>12} | {:>12} | IC {:.0}%",
+        "INDICADOR",
+        "N",
+        "MEDIA",
+        "DESV. EST.",
+        confidence * 100.0
+    );
+    for (name, estimate) in estimates {
+        match estimate {
+            Some(estimate) => println!(
+                "{:<30} | {:>4} | {:>12.4} | {:>12.4} | {}",
+                name,
+                estimate.n,
+                estimate.mean,
+                estimate.std_dev,
+                match estimate.interval() {
+                    Some((lower, upper)) => format!("[{:.4}, {:.4}]", lower, upper),
+                    None => "-".to_string(),
+                }
+            ),
+            None => println!("{:<30} | {:>4} | {:>12} | {:>12} | -", name, 0, "-", "-"),
+        }
+    }
+}
@@ -0,0 +1,92 @@
+use rand::rngs::StdRng;
+use rand::SeedableRng;
+
+// Streams de números aleatorios de la simulación. Cada proceso estocástico
+// tiene su propio generador, así cambiar cuántos números consume uno de ellos
+// no altera la secuencia que ven los demás.
+pub struct SimRng {
+    pub arrivals: StdRng,
+    pub payment_choice: StdRng,
+    pub refuel_time: StdRng,
+    pub payment_time: StdRng,
+    pub queue_ties: StdRng,
+    pub balking: StdRng,
+    pub patience: StdRng,
+    pub fuel_choice: StdRng,
+    pub litres: StdRng,
+    pub vehicle_choice: StdRng,
+    pub failures: StdRng,
+}
+
+impl SimRng {
+    pub fn new(seed: u64) -> SimRng {
+        SimRng {
+            arrivals: stream(seed, 0),
+            payment_choice: stream(seed, 1),
+            refuel_time: stream(seed, 2),
+            payment_time: stream(seed, 3),
+            queue_ties: stream(seed, 4),
+            balking: stream(seed, 5),
+            patience: stream(seed, 6),
+            fuel_choice: stream(seed, 7),
+            litres: stream(seed, 8),
+            vehicle_choice: stream(seed, 9),
+            failures: stream(seed, 10),
+        }
+    }
+}
+
+// Semilla del stream `index` a partir de la semilla maestra. La semilla maestra
+// se mezcla antes de sumarle el índice: si solo se sumara un múltiplo del
+// índice, la semilla `s + k * G` repetiría el stream 0 de `s` como su stream k.
+// Los streams nuevos se agregan con un índice nuevo para no alterar los demás.
+fn stream(seed: u64, index: u64) -> StdRng {
+    StdRng::seed_from_u64(splitmix64(splitmix64(seed).wrapping_add(index)))
+}
+
+// Semilla de la réplica `index` de un estudio con semilla maestra `seed`.
+// Solo depende de ambos valores, así cada réplica es reproducible por sí sola.
+pub fn replication_seed(seed: u64, index: u64) -> u64 {
+    splitmix64(seed.wrapping_add(index.wrapping_mul(GOLDEN_GAMMA)))
+}
+
+const GOLDEN_GAMMA: u64 = 0x9E37_79B9_7F4A_7C15;
+
+// Un paso de splitmix64 desde el estado `z`.
+fn splitmix64(z: u64) -> u64 {
+    let mut z = z.wrapping_add(GOLDEN_GAMMA);
+    z = (z ^ (z >> 30)).wrapping_mul(0xBF58_476D_1CE4_E5B9);
+    z = (z ^ (z >> 27)).wrapping_mul(0x94D0_49BB_1331_11EB);
+    z ^ (z >> 31)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::{stream, SimRng, GOLDEN_GAMMA};
+    use rand::Rng;
+
+    fn draws(rng: &mut impl Rng) -> Vec<u64> {
+        (0..8).map(|_| rng.gen()).collect()
+    }
+
+    #[test]
+    fn same_seed_gives_same_streams() {
+        let mut a = SimRng::new(42);
+        let mut b = SimRng::new(42);
+        assert_eq!(draws(&mut a.arrivals), draws(&mut b.arrivals));
+        assert_eq!(draws(&mut a.failures), draws(&mut b.failures));
+        assert_ne!(draws(&mut a.arrivals), draws(&mut a.refuel_time));
+    }
+
+    #[test]
+    fn streams_do_not_overlap_across_seeds() {
+        let seed: u64 = 7;
+        for index in 1..11u64 {
+            let shifted = seed.wrapping_add(index.wrapping_mul(GOLDEN_GAMMA));
+            assert_ne!(
+                draws(&mut stream(seed, 0)),
+                draws(&mut stream(shifted, index))
+            );
+        }
+    }
+}
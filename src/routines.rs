@@ -1,12 +1,13 @@
-use crate::Event;
-use crate::EventQueue;
-use crate::{Customer, PaymentMethod};
+use crate::event::{clock_hours, OutageCause};
+use crate::scenario::{ArrivalCurve, Failures, Jockeying, OutagePolicy, Resupply, Scenario};
+use crate::selection::{checked_pump, checked_queue, SelectionContext};
+use crate::SimState;
+use crate::{Customer, CustomerData, CustomerRecord, Fuel};
+use crate::{Event, EventKind};
 
-use rand::seq::SliceRandom;
 use rand::Rng;
 use rand_distr::Exp;
 use rand_distr::{Distribution, Normal};
-use std::collections::HashMap;
 //use std::env;
 
 #[allow(unused_macros)]
@@ -37,254 +38,1151 @@ pub fn format_customer_queues(customer_queues: &[Vec<Customer>]) -> String {
     format!("[{}]", queue_string.join(" "))
 }
 
-pub fn any_available(arr: &[i64]) -> (bool, Option<u64>) {
-    let zero_indexes: Vec<u64> = arr
-        .iter()
-        .enumerate()
-        .filter_map(
-            |(index, &value)| {
-                if value == 0 {
-                    Some(index as u64)
-                } else {
-                    None
-                }
-            },
-        )
-        .collect();
+// Saca de la cola al primer cliente que el surtidor puede atender.
+pub fn process_customer_queues<F: Fn(&Customer) -> bool>(
+    customer_queues: &mut [Vec<Customer>],
+    index: usize,
+    accepts: F,
+) -> Option<Customer> {
+    if !customer_queues.is_empty() {
+        let queue = &mut customer_queues[index];
+        if let Some(position) = queue.iter().position(accepts) {
+            return Some(queue.remove(position));
+        }
+    }
+    None
+}
 
-    let has_zero = !zero_indexes.is_empty();
-    let random_index = zero_indexes.choose(&mut rand::thread_rng()).copied();
+// Minuto del día llevado a [0, 1), para cualquier largo de corrida.
+fn normalize(minutes: f64) -> f64 {
+    minutes.rem_euclid(1440.0) / 1440.0
+}
 
-    (has_zero, random_index)
+fn beta_distr(x: f64, curve: &ArrivalCurve) -> f64 {
+    let numerator = x.powf(curve.alpha) * (1.0 - x).powf(curve.beta);
+    numerator / curve.normalizer + curve.baseline
 }
 
-pub fn get_shortest_or_random_index(customer_queues: &[Vec<Customer>]) -> usize {
-    let mut shortest_indexes = Vec::new();
-    let mut shortest_length = usize::MAX;
+// Desc: Routines for the simulation used in main.rs
+pub fn time_routine(state: &mut SimState) -> Option<Event> {
+    let event = state.event_queue.pop()?;
+    state.sim_time = event.scheduled_time;
+    Some(event)
+}
 
-    for (index, queue) in customer_queues.iter().enumerate() {
-        let queue_length = queue.len();
-        if queue_length < shortest_length {
-            shortest_length = queue_length;
-            shortest_indexes.clear();
-            shortest_indexes.push(index);
-        } else if queue_length == shortest_length {
-            shortest_indexes.push(index);
+pub fn arrive_routine(
+    state: &mut SimState,
+    e: &mut Event,
+    scenario: &Scenario,
+    customer_data: &mut CustomerData,
+) {
+    let sim_time = state.sim_time;
+    state.customer_count += 1;
+    let arrival_rate = beta_distr(normalize(sim_time), &scenario.arrivals);
+    let exp_distr = Exp::new(scenario.arrivals.scale / arrival_rate).unwrap();
+    let next_arrival_time = state.rng.arrivals.sample(exp_distr);
+
+    //println!("Current arrival rate {} -> {}", sim_time, arrival_rate);
+    //println!("Next arrival in {}", next_arrival_time);
+
+    let queue_event = Event::new(EventKind::Queue, e.customer.clone(), sim_time);
+
+    customer_data.insert(e.customer.id, CustomerRecord::new(&e.customer, sim_time));
+
+    state.event_queue.push(queue_event);
+
+    let new_customer = Customer::new(
+        state.customer_count,
+        sim_time + next_arrival_time,
+        scenario,
+        &mut state.rng,
+    );
+    let new_event = Event::new(
+        EventKind::Arrive,
+        new_customer,
+        sim_time + next_arrival_time,
+    );
+    state.event_queue.push(new_event);
+}
+
+pub fn queue_routine(
+    state: &mut SimState,
+    e: &mut Event,
+    scenario: &Scenario,
+    customer_data: &mut CustomerData,
+) {
+    let sim_time = state.sim_time;
+    // Su combustible está agotado: se va sin cargar.
+    if state.tanks.stocked_out(e.customer.fuel) {
+        state
+            .tanks
+            .lose(e.customer.fuel, e.customer.litres.unwrap_or(0.0));
+        if let Some(record) = customer_data.get_mut(&e.customer.id) {
+            record.stocked_out = true;
         }
+        return;
     }
-
-    let mut rng = rand::thread_rng();
-    if shortest_indexes.is_empty() {
-        rng.gen_range(0..customer_queues.len())
+    let free: Vec<usize> = (0..state.fuel_stations.len())
+        .filter(|&station| state.can_start(station, &e.customer))
+        .collect();
+    let mut candidates: Vec<usize> = (0..state.customer_queues.len())
+        .filter(|&queue| state.queue_serves(queue, &e.customer))
+        .collect();
+    // Evita las colas sin surtidores en servicio, si tiene alternativa.
+    if candidates
+        .iter()
+        .any(|&queue| state.queue_in_service(queue))
+    {
+        candidates.retain(|&queue| state.queue_in_service(queue));
+    }
+    // Ningún surtidor de esta configuración puede atenderlo (combustible o
+    // categoría de vehículo): se va, y se cuenta aparte del balking.
+    if candidates.is_empty() {
+        if let Some(record) = customer_data.get_mut(&e.customer.id) {
+            record.unserved = true;
+        }
+        return;
+    }
+    let context = SelectionContext {
+        customer: &e.customer,
+        queues: &state.customer_queues,
+        queue_stations: &state.queue_stations,
+        candidates: &candidates,
+        scenario,
+    };
+    if !free.is_empty() {
+        let station = checked_pump(
+            state.policy.as_mut(),
+            &free,
+            &context,
+            &mut state.rng.queue_ties,
+        );
+        // Solo quien entra a una cola o va directo a un surtidor tiene
+        // instante de entrada.
+        if let Some(record) = customer_data.get_mut(&e.customer.id) {
+            record.queue_entry = Some(sim_time);
+        }
+        let refuel_event = Event::new(EventKind::Refuel { station }, e.customer.clone(), sim_time);
+        state.event_queue.push(refuel_event);
+        state.fuel_stations[station] = 1;
+        state.stats.pump_busy(station, sim_time);
+        state.update_entry_blocking(station);
     } else {
-        shortest_indexes[rng.gen_range(0..shortest_indexes.len())]
+        // El balking se decide antes de consultar la política, para que quien se
+        // va no mueva su estado ni el sorteo de empates.
+        let shortest_queue = candidates
+            .iter()
+            .map(|&queue| state.customer_queues[queue].len())
+            .min()
+            .unwrap_or(0);
+        let balk_probability = scenario.balking.probability(shortest_queue);
+        if balk_probability > 0.0 && state.rng.balking.gen::<f64>() < balk_probability {
+            if let Some(record) = customer_data.get_mut(&e.customer.id) {
+                record.balked = true;
+            }
+            return;
+        }
+        let queue_index = checked_queue(state.policy.as_mut(), &context, &mut state.rng.queue_ties);
+        if let Some(record) = customer_data.get_mut(&e.customer.id) {
+            record.queue_entry = Some(sim_time);
+        }
+        state.customer_queues[queue_index].push(e.customer.clone());
+        let queue_length = state.customer_queues[queue_index].len();
+        state
+            .stats
+            .queue_changed(queue_index, sim_time, queue_length);
+        if let Some(patience) = e.customer.patience {
+            let renege_event =
+                Event::new(EventKind::Renege, e.customer.clone(), sim_time + patience);
+            state.event_queue.push(renege_event);
+        }
     }
 }
 
-pub fn process_customer_queues(
-    customer_queues: &mut [Vec<Customer>],
-    index: usize,
-) -> Option<Customer> {
-    if !customer_queues.is_empty() {
-        if let Some(customer) = customer_queues[index].first().cloned() {
-            customer_queues[index].remove(0);
-            return Some(customer);
-        }
+// Duración normal truncada en cero: una negativa dejaría el evento en el pasado.
+fn normal_duration<R: Rng>(mean: f64, std_dev: f64, rng: &mut R) -> f64 {
+    Normal::new(mean, std_dev).unwrap().sample(rng).max(0.0)
+}
+
+pub fn refuel_routine(
+    state: &mut SimState,
+    e: &mut Event,
+    station: usize,
+    scenario: &Scenario,
+    customer_data: &mut CustomerData,
+) {
+    // Sin bombero libre, espera en el surtidor.
+    if !state.attendants.seize(state.sim_time) {
+        state
+            .attendants
+            .wait(e.customer.clone(), station, state.sim_time);
+        return;
     }
-    None
+    start_refuel(state, e.customer.clone(), station, scenario, customer_data);
 }
 
-fn normalize(minutes: u16) -> f64 {
-    let range_min = 0;
-    let range_max = 1440;
-    let target_min = 0.0;
-    let target_max = 1.0;
+fn start_refuel(
+    state: &mut SimState,
+    customer: Customer,
+    station: usize,
+    scenario: &Scenario,
+    customer_data: &mut CustomerData,
+) {
+    let sim_time = state.sim_time;
+    // La atención empieza al llegar al surtidor, haya hecho fila o no, o al
+    // llegar el bombero si tuvo que esperarlo.
+    if let Some(record) = customer_data.get_mut(&customer.id) {
+        record.service_start = Some(sim_time);
+        record.station = Some(station);
+        record.litres = customer.litres;
+    }
+    let refuel_time: f64 = match customer.litres {
+        Some(litres) => scenario.dispensing.refuel_time(litres, station),
+        None => normal_duration(
+            scenario.refuel.mean,
+            scenario.refuel.std_dev,
+            &mut state.rng.refuel_time,
+        ),
+    };
+    let payment_event = Event::new(
+        EventKind::Payment { station },
+        customer,
+        sim_time + refuel_time,
+    );
+    state.event_queue.push(payment_event);
+}
 
-    let clamped_minutes = minutes % (range_max + 1); // Wrap around the value if it exceeds range_max
+// Libera al bombero y los que quedan libres atienden a quienes los esperaban.
+fn release_attendant(state: &mut SimState, scenario: &Scenario, customer_data: &mut CustomerData) {
+    state.attendants.release(state.sim_time);
+    dispatch_attendants(state, scenario, customer_data);
+}
 
-    let normalized = (clamped_minutes - range_min) as f64 / (range_max - range_min) as f64;
+// Los bomberos libres toman a los clientes en espera, por orden de llegada.
+fn dispatch_attendants(
+    state: &mut SimState,
+    scenario: &Scenario,
+    customer_data: &mut CustomerData,
+) {
+    let sim_time = state.sim_time;
+    while let Some((customer, station, since)) = state.attendants.next_waiting(sim_time) {
+        if let Some(record) = customer_data.get_mut(&customer.id) {
+            record.attendant_wait += sim_time - since;
+        }
+        start_refuel(state, customer, station, scenario, customer_data);
+    }
+}
 
-    // Adjust the normalized value to match the desired range
-    let adjusted_normalized = (normalized - target_min) / (target_max - target_min);
+pub fn payment_routine(
+    state: &mut SimState,
+    e: &mut Event,
+    station: usize,
+    scenario: &Scenario,
+    customer_data: &mut CustomerData,
+) {
+    let sim_time = state.sim_time;
+    if let Some(record) = customer_data.get_mut(&e.customer.id) {
+        record.refuel_end = Some(sim_time);
+    }
+    // La carga terminada se descuenta del estanque.
+    if let Some(litres) = e.customer.litres {
+        let dispensed = state.tanks.draw(e.customer.fuel, litres, sim_time);
+        if let Some(record) = customer_data.get_mut(&e.customer.id) {
+            record.litres = Some(dispensed);
+        }
+        order_tanker(state, e.customer.fuel, scenario);
+        if sold_out(state, e.customer.fuel, scenario) {
+            drop_stocked_out(state, e.customer.fuel, customer_data);
+        }
+    }
+    let pays_at_counter = scenario.cashier.pays_at_counter(e.customer.payment_method);
+    // El bombero no cobra a quien paga en la caja.
+    if !scenario.attendants.takes_payment() || pays_at_counter {
+        release_attendant(state, scenario, customer_data);
+    }
+    if pays_at_counter {
+        if let Some(record) = customer_data.get_mut(&e.customer.id) {
+            record.paid_at_counter = true;
+        }
+        let counter_event = Event::new(
+            EventKind::CounterArrival { station },
+            e.customer.clone(),
+            sim_time + scenario.cashier.walk_time / 2.0,
+        );
+        state.event_queue.push(counter_event);
+        return;
+    }
+    let payment_time = sample_payment_time(state, &e.customer, scenario);
 
-    adjusted_normalized
+    let departure_event = Event::new(
+        EventKind::Departure { station },
+        e.customer.clone(),
+        sim_time + payment_time,
+    );
+    state.event_queue.push(departure_event);
 }
 
-fn beta_distr(x: f64) -> f64 {
-    let numerator = x.powf(7.0) * (1.0 - x).powf(5.075);
-    let denominator = 0.0000908345394559;
-    numerator / denominator + 0.1
+// El combustible se agotó y no viene ningún camión.
+fn sold_out(state: &SimState, fuel: Fuel, scenario: &Scenario) -> bool {
+    state.tanks.stocked_out(fuel) && matches!(scenario.inventory.resupply, Resupply::None)
 }
 
-fn update_value(
-    customer_data: &mut HashMap<u64, (u8, f64, f64, f64, u8)>,
-    id: u64,
-    index: usize,
-    value: f64
-) -> Result<(), String> {
-    if let Some(tuple) = customer_data.get_mut(&id) {
-        match index {
-            0 => tuple.0 = value as u8,
-            1 => tuple.1 = value,
-            2 => tuple.2 = value,
-            3 => tuple.3 = value,
-            4 => tuple.4 = value as u8,
-            _ => {
-                return Err(String::from("Invalid index"));
+// Los clientes en cola del combustible agotado se van sin cargar.
+fn drop_stocked_out(state: &mut SimState, fuel: Fuel, customer_data: &mut CustomerData) {
+    let sim_time = state.sim_time;
+    for queue_index in 0..state.customer_queues.len() {
+        let queue = &mut state.customer_queues[queue_index];
+        if queue.iter().all(|customer| customer.fuel != fuel) {
+            continue;
+        }
+        let (lost, kept): (Vec<Customer>, Vec<Customer>) =
+            queue.drain(..).partition(|customer| customer.fuel == fuel);
+        *queue = kept;
+        let queue_length = queue.len();
+        state
+            .stats
+            .queue_changed(queue_index, sim_time, queue_length);
+        for customer in lost {
+            state.tanks.lose(fuel, customer.litres.unwrap_or(0.0));
+            if let Some(record) = customer_data.get_mut(&customer.id) {
+                record.departure = Some(sim_time);
+                record.stocked_out = true;
             }
         }
-    } else {
-        return Err(String::from("ID not found in the HashMap"));
     }
+}
 
-    Ok(())
+fn sample_payment_time(state: &mut SimState, customer: &Customer, scenario: &Scenario) -> f64 {
+    let params = scenario.payment.params(&customer.payment_method);
+    normal_duration(
+        params.mean * scenario.payment.factor,
+        params.std_dev,
+        &mut state.rng.payment_time,
+    )
 }
 
-// Desc: Routines for the simulation used in main.rs
-pub fn time_routine(event_queue: &mut EventQueue, clock: &mut f64) -> Option<Event> {
-    if !event_queue.q.is_empty() {
-        let event = event_queue.q.remove(0);
-        *clock = event.scheduled_time as f64;
-        Some(event)
+// Llegada a la caja: paga si hay un cajero libre o hace fila.
+pub fn counter_arrival_routine(
+    state: &mut SimState,
+    e: &mut Event,
+    station: usize,
+    scenario: &Scenario,
+) {
+    let sim_time = state.sim_time;
+    if state.counter.seize(sim_time) {
+        start_counter_payment(state, e.customer.clone(), station, scenario);
     } else {
-        None
+        state.counter.wait(e.customer.clone(), station, sim_time);
     }
 }
 
-pub fn arrive_routine(
-    event_queue: &mut EventQueue,
-    sim_time: &mut f64,
+fn start_counter_payment(
+    state: &mut SimState,
+    customer: Customer,
+    station: usize,
+    scenario: &Scenario,
+) {
+    let payment_time = sample_payment_time(state, &customer, scenario);
+    let done_event = Event::new(
+        EventKind::CounterDone { station },
+        customer,
+        state.sim_time + payment_time,
+    );
+    state.event_queue.push(done_event);
+}
+
+// Terminó de pagar en la caja: vuelve al auto y el cajero atiende al
+// siguiente de la fila.
+pub fn counter_done_routine(
+    state: &mut SimState,
     e: &mut Event,
-    customer_count: &mut u64,
-    customer_data: &mut HashMap<u64, (u8, f64, f64, f64, u8)>,
+    station: usize,
+    scenario: &Scenario,
+    customer_data: &mut CustomerData,
 ) {
-    *customer_count += 1;
-    let arrival_rate = beta_distr(normalize(*sim_time as u16));
-    let exp_distr = Exp::new(10.0 / arrival_rate).unwrap();
-    let next_arrival_time = rand::thread_rng().sample(exp_distr);
+    let sim_time = state.sim_time;
+    let departure_event = Event::new(
+        EventKind::Departure { station },
+        e.customer.clone(),
+        sim_time + scenario.cashier.walk_time / 2.0,
+    );
+    state.event_queue.push(departure_event);
+    state.counter.release(sim_time);
+    if let Some((customer, station, since)) = state.counter.next_waiting(sim_time) {
+        if let Some(record) = customer_data.get_mut(&customer.id) {
+            record.counter_wait += sim_time - since;
+        }
+        start_counter_payment(state, customer, station, scenario);
+    }
+}
 
-    //println!("Current arrival rate {} -> {}", *sim_time, arrival_rate);
-    //println!("Next arrival in {}", next_arrival_time);
+// Devuelve cuántos clientes salieron: ninguno si el auto de adelante lo
+// bloquea, o varios si libera a los que esperaban detrás suyo.
+pub fn departure_routine(
+    state: &mut SimState,
+    e: &mut Event,
+    station: usize,
+    scenario: &Scenario,
+    customer_data: &mut CustomerData,
+) -> u64 {
+    let sim_time = state.sim_time;
+    e.customer.total_time = sim_time - e.customer.arrive_time;
+    if let Some(record) = customer_data.get_mut(&e.customer.id) {
+        record.payment_end = Some(sim_time);
+    }
+    // Quien pagó en la caja ya soltó al bombero al ir a la tienda.
+    if scenario.attendants.takes_payment()
+        && !scenario.cashier.pays_at_counter(e.customer.payment_method)
+    {
+        release_attendant(state, scenario, customer_data);
+    }
+    // Con un auto adelante en la pista, espera en el surtidor hasta que salga.
+    if state.blocked_ahead(station) {
+        state.blocked[station] = Some(e.customer.id);
+        return 0;
+    }
+    let (lane, _) = state.station_lane[station];
+    let lane_stations = state.lanes[lane].clone();
+    let occupied: Vec<i64> = lane_stations
+        .iter()
+        .map(|&lane_station| state.fuel_stations[lane_station])
+        .collect();
 
-    let queue_event = Event::new(1, e.customer.clone(), *sim_time, None);
+    leave(state, station, e.customer.id, customer_data);
+    let mut departed = vec![station];
+    // Los que esperaban detrás salen en cadena.
+    for &behind in &lane_stations {
+        if let Some(id) = state.blocked[behind] {
+            if !state.blocked_ahead(behind) {
+                state.blocked[behind] = None;
+                if let Some(payment_end) = customer_data.get(&id).and_then(|r| r.payment_end) {
+                    state.stats.exit_blocked(behind, sim_time - payment_end);
+                }
+                leave(state, behind, id, customer_data);
+                departed.push(behind);
+            }
+        }
+    }
+    // El siguiente de la cola pasa directo a cada surtidor libre al que se
+    // puede llegar, de adelante hacia atrás.
+    for &free in &lane_stations {
+        if state.available(free) {
+            start_from_queue(state, free);
+        }
+    }
+    for (&lane_station, &before) in lane_stations.iter().zip(&occupied) {
+        match (before, state.fuel_stations[lane_station]) {
+            (1, 0) => state.stats.pump_idle(lane_station, sim_time),
+            (0, 1) => state.stats.pump_busy(lane_station, sim_time),
+            _ => {}
+        }
+    }
+    state.update_entry_blocking(station);
+    for &station in &departed {
+        jockey(state, station, scenario, customer_data);
+    }
 
-    match e.customer.payment_method {
-        PaymentMethod::Efectivo => customer_data.insert(e.customer.id, (0, *sim_time, 0.0, 0.0, 0)),
-        PaymentMethod::Tarjeta => customer_data.insert(e.customer.id, (1, *sim_time, 0.0, 0.0, 0)),
-        PaymentMethod::CopecApp => customer_data.insert(e.customer.id, (2, *sim_time, 0.0, 0.0, 0)),
-    };
+    /* println!(
+        "Customer {} que pagó con {:?} terminó después de {:.2} segs.",
+        e.customer.id,
+        e.customer.payment_method,
+        e.customer.total_time
+    ); */
+    departed.len() as u64
+}
 
-    event_queue.add(queue_event);
+// El primer cliente de la cola del surtidor que este puede atender pasa
+// directo a cargar. Las estadísticas del surtidor quedan a cargo de quien llama.
+fn start_from_queue(state: &mut SimState, station: usize) -> bool {
+    let sim_time = state.sim_time;
+    let queue = state.station_queue[station];
+    let access = &state.station_access[station];
+    let tanks = &state.tanks;
+    let customer = process_customer_queues(&mut state.customer_queues, queue, |customer| {
+        access.accepts(customer) && tanks.open(customer.fuel)
+    });
+    match customer {
+        Some(customer) => {
+            let refuel_event = Event::new(EventKind::Refuel { station }, customer, sim_time);
+            state.event_queue.push(refuel_event);
+            state.fuel_stations[station] = 1;
+            let queue_length = state.customer_queues[queue].len();
+            state.stats.queue_changed(queue, sim_time, queue_length);
+            true
+        }
+        None => false,
+    }
+}
 
-    let new_customer = Customer::new(*customer_count, *sim_time + next_arrival_time);
-    let new_event = Event::new(0, new_customer, *sim_time + next_arrival_time, None);
-    event_queue.add(new_event);
+// El cliente deja el surtidor y sale de la estación.
+fn leave(state: &mut SimState, station: usize, id: u64, customer_data: &mut CustomerData) {
+    state.fuel_stations[station] = 0;
+    if let Some(record) = customer_data.get_mut(&id) {
+        record.departure = Some(state.sim_time);
+        record.served = true;
+    }
 }
 
-pub fn queue_routine(
-    event_queue: &mut EventQueue,
-    sim_time: &mut f64,
+// Si el cliente sigue en alguna cola, la abandona; si ya pasó al surtidor el
+// evento quedó obsoleto y no hace nada.
+pub fn renege_routine(state: &mut SimState, e: &mut Event, customer_data: &mut CustomerData) {
+    let sim_time = state.sim_time;
+    for (queue_index, queue) in state.customer_queues.iter_mut().enumerate() {
+        if let Some(position) = queue.iter().position(|c| c.id == e.customer.id) {
+            queue.remove(position);
+            let queue_length = queue.len();
+            state
+                .stats
+                .queue_changed(queue_index, sim_time, queue_length);
+            if let Some(record) = customer_data.get_mut(&e.customer.id) {
+                record.departure = Some(sim_time);
+                record.reneged = true;
+            }
+            return;
+        }
+    }
+}
+
+// Al liberarse `station`, el último cliente de la cola más larga (la de menor
+// índice si hay empate) se cambia a la cola que alimenta a `station` si la
+// diferencia de largo lo justifica. Llega a la nueva cola tras el retardo del
+// escenario.
+fn jockey(
+    state: &mut SimState,
+    station: usize,
+    scenario: &Scenario,
+    customer_data: &mut CustomerData,
+) {
+    let (difference, delay) = match scenario.jockeying {
+        Jockeying::None => return,
+        Jockeying::ShorterQueue { difference, delay } => (difference, delay),
+    };
+    if state.out_of_service[station] > 0 {
+        return;
+    }
+    let sim_time = state.sim_time;
+    let target = state.station_queue[station];
+    // Largo que debe superar una cola para que valga la pena cambiarse. Con
+    // `difference` 0 y la cola propia vacía queda en 0: una cola vacía no
+    // tiene a quién cambiar.
+    let mut longest_length = (state.customer_queues[target].len() + difference).saturating_sub(1);
+    let mut longest = None;
+    for (queue_index, queue) in state.customer_queues.iter().enumerate() {
+        if queue_index != target && queue.len() > longest_length {
+            longest_length = queue.len();
+            longest = Some(queue_index);
+        }
+    }
+    if let Some(queue_index) = longest {
+        // Solo se cambia si la nueva cola le sirve.
+        match state.customer_queues[queue_index].last() {
+            Some(customer) if state.queue_serves(target, customer) => {}
+            _ => return,
+        }
+        if let Some(customer) = state.customer_queues[queue_index].pop() {
+            let queue_length = state.customer_queues[queue_index].len();
+            state
+                .stats
+                .queue_changed(queue_index, sim_time, queue_length);
+            if let Some(record) = customer_data.get_mut(&customer.id) {
+                record.jockeys += 1;
+            }
+            let rejoin_event = Event::new(
+                EventKind::Rejoin { queue: target },
+                customer,
+                sim_time + delay,
+            );
+            state.event_queue.push(rejoin_event);
+        }
+    }
+}
+
+// Llegada de un cliente a la cola a la que se cambió. Si uno de sus
+// surtidores quedó libre pasa directo; si su paciencia se agotó durante el
+// cambio, abandona.
+pub fn rejoin_routine(
+    state: &mut SimState,
     e: &mut Event,
-    fuel_stations: &mut [i64],
-    customer_queues: &mut Vec<Vec<Customer>>,
-    customer_data: &mut HashMap<u64, (u8, f64, f64, f64, u8)>,
+    queue: usize,
+    scenario: &Scenario,
+    customer_data: &mut CustomerData,
 ) {
-    let (available_station, idx) = any_available(&fuel_stations);
-    if available_station {
-        if let Some(station_idx) = idx {
-            let refuel_event = Event::new(2, e.customer.clone(), *sim_time, idx);
-            event_queue.add(refuel_event);
-            fuel_stations[station_idx as usize] = 1;
+    let sim_time = state.sim_time;
+    let record = match customer_data.get_mut(&e.customer.id) {
+        Some(record) => record,
+        None => return,
+    };
+    // Su combustible se agotó mientras se cambiaba de cola.
+    if sold_out(state, e.customer.fuel, scenario) {
+        state
+            .tanks
+            .lose(e.customer.fuel, e.customer.litres.unwrap_or(0.0));
+        record.departure = Some(sim_time);
+        record.stocked_out = true;
+        return;
+    }
+    // Abandona en el instante en que se le acabó la paciencia, no al llegar.
+    if let (Some(patience), Some(queue_entry)) = (e.customer.patience, record.queue_entry) {
+        if sim_time >= queue_entry + patience {
+            record.departure = Some(queue_entry + patience);
+            record.reneged = true;
+            return;
         }
+    }
+    enter_queue(state, e.customer.clone(), queue);
+}
+
+// El cliente pasa directo a un surtidor libre de la cola o se pone al final.
+fn enter_queue(state: &mut SimState, customer: Customer, queue: usize) {
+    let sim_time = state.sim_time;
+    let free_station = state.queue_stations[queue]
+        .iter()
+        .copied()
+        .find(|&station| state.can_start(station, &customer));
+    if let Some(station) = free_station {
+        let refuel_event = Event::new(EventKind::Refuel { station }, customer, sim_time);
+        state.event_queue.push(refuel_event);
+        state.fuel_stations[station] = 1;
+        state.stats.pump_busy(station, sim_time);
+        state.update_entry_blocking(station);
     } else {
-        let queue_index = get_shortest_or_random_index(customer_queues);
-        customer_queues[queue_index].push(e.customer.clone());
-        let _ = update_value(customer_data, e.customer.id, 2, *sim_time);
+        state.customer_queues[queue].push(customer);
+        let queue_length = state.customer_queues[queue].len();
+        state.stats.queue_changed(queue, sim_time, queue_length);
     }
 }
 
-pub fn refuel_routine(event_queue: &mut EventQueue, sim_time: &mut f64, e: &mut Event) {
-    let normal = Normal::new(2.0, 0.15).unwrap();
-    let refuel_time: f64 = normal.sample(&mut rand::thread_rng());
-    let payment_event = Event::new(
-        3,
-        e.customer.clone(),
-        *sim_time + refuel_time,
-        e.chosen_queue,
+// Agenda los primeros camiones: el programado, o un pedido si algún estanque
+// parte bajo el punto de reorden.
+pub fn schedule_resupply(state: &mut SimState, scenario: &Scenario) {
+    let fuels: Vec<Fuel> = state.tanks.tracked().map(|(fuel, _)| fuel).collect();
+    for fuel in fuels {
+        match scenario.inventory.resupply {
+            Resupply::Scheduled { first, .. } => {
+                let tanker_event =
+                    Event::without_customer(EventKind::TankerArrival { fuel }, first);
+                state.event_queue.push(tanker_event);
+            }
+            Resupply::ReorderPoint { .. } => order_tanker(state, fuel, scenario),
+            Resupply::None => {}
+        }
+    }
+}
+
+// Con la política (s, Q), pide un camión si el estanque bajó del punto de
+// reorden y no hay uno en camino.
+fn order_tanker(state: &mut SimState, fuel: Fuel, scenario: &Scenario) {
+    let resupply = &scenario.inventory.resupply;
+    if let Resupply::ReorderPoint { lead_time, .. } = resupply {
+        if state.tanks.place_order(fuel, resupply) {
+            let tanker_event = Event::without_customer(
+                EventKind::TankerArrival { fuel },
+                state.sim_time + lead_time,
+            );
+            state.event_queue.push(tanker_event);
+        }
+    }
+}
+
+// El camión empieza a descargar y el combustible se cierra. Con camiones
+// programados se agenda el siguiente.
+pub fn tanker_arrival_routine(state: &mut SimState, fuel: Fuel, scenario: &Scenario) {
+    let sim_time = state.sim_time;
+    state.tanks.start_unloading(fuel, sim_time);
+    let unloaded_event = Event::without_customer(
+        EventKind::TankerUnloaded { fuel },
+        sim_time + scenario.inventory.unloading_time,
     );
-    event_queue.add(payment_event);
+    state.event_queue.push(unloaded_event);
+    if let Resupply::Scheduled { interval, .. } = scenario.inventory.resupply {
+        let tanker_event =
+            Event::without_customer(EventKind::TankerArrival { fuel }, sim_time + interval);
+        state.event_queue.push(tanker_event);
+    }
 }
 
-pub fn payment_routine(event_queue: &mut EventQueue, sim_time: &mut f64, e: &mut Event) {
-    let payment_time: f64;
-    let factor: f64 = 1.0;
-    match e.customer.payment_method {
-        PaymentMethod::Efectivo => {
-            payment_time = Normal::new(0.875 * factor, 0.1)
-                .unwrap()
-                .sample(&mut rand::thread_rng())
+// Termina la descarga: el combustible vuelve a venderse y los surtidores
+// libres toman a los clientes que lo esperaban en cola.
+pub fn tanker_unloaded_routine(state: &mut SimState, fuel: Fuel, scenario: &Scenario) {
+    let sim_time = state.sim_time;
+    state
+        .tanks
+        .finish_unloading(fuel, &scenario.inventory.resupply, sim_time);
+    order_tanker(state, fuel, scenario);
+    for station in 0..state.fuel_stations.len() {
+        if state.available(station) && start_from_queue(state, station) {
+            state.stats.pump_busy(station, sim_time);
+            state.update_entry_blocking(station);
         }
-        PaymentMethod::Tarjeta => {
-            payment_time = Normal::new(0.425 * factor, 0.075)
-                .unwrap()
-                .sample(&mut rand::thread_rng())
+    }
+}
+
+// Agenda la primera falla de cada surtidor y las mantenciones programadas.
+pub fn schedule_breakdowns(state: &mut SimState, scenario: &Scenario) {
+    let stations = state.fuel_stations.len();
+    if let Failures::Random {
+        time_to_failure, ..
+    } = scenario.breakdowns.failures
+    {
+        for station in 0..stations {
+            let failure_time = time_to_failure.sample(&mut state.rng.failures);
+            let failure_event = Event::without_customer(
+                EventKind::PumpDown {
+                    station,
+                    cause: OutageCause::Failure,
+                },
+                failure_time,
+            );
+            state.event_queue.push(failure_event);
         }
-        PaymentMethod::CopecApp => {
-            payment_time = Normal::new(0.275 * factor, 0.055)
-                .unwrap()
-                .sample(&mut rand::thread_rng())
+    }
+    for (window, maintenance) in scenario.breakdowns.maintenance.iter().enumerate() {
+        if maintenance.pump < stations {
+            let maintenance_event = Event::without_customer(
+                EventKind::PumpDown {
+                    station: maintenance.pump,
+                    cause: OutageCause::Maintenance { window },
+                },
+                maintenance.start,
+            );
+            state.event_queue.push(maintenance_event);
         }
     }
+}
 
-    let departure_event = Event::new(
-        4,
-        e.customer.clone(),
-        *sim_time + payment_time,
-        e.chosen_queue,
-    );
-    event_queue.add(departure_event);
+// El surtidor sale de servicio: se agenda su vuelta y, según el escenario,
+// la próxima mantención y el traslado de su cola.
+pub fn pump_down_routine(
+    state: &mut SimState,
+    station: usize,
+    cause: OutageCause,
+    scenario: &Scenario,
+) {
+    let sim_time = state.sim_time;
+    state.out_of_service[station] += 1;
+    let outage = state.stats.outage_started(station, cause, sim_time, true);
+    let duration = match cause {
+        OutageCause::Failure => match scenario.breakdowns.failures {
+            Failures::Random { repair_time, .. } => repair_time.sample(&mut state.rng.failures),
+            // Sin modelo de fallas no se agendan caídas por falla.
+            Failures::None => unreachable!("falla de surtidor sin breakdowns.failures"),
+        },
+        OutageCause::Maintenance { window } => {
+            let maintenance = scenario.breakdowns.maintenance[window];
+            if let Some(interval) = maintenance.interval {
+                let next_event = Event::without_customer(
+                    EventKind::PumpDown { station, cause },
+                    sim_time + interval,
+                );
+                state.event_queue.push(next_event);
+            }
+            maintenance.duration
+        }
+    };
+    let up_event =
+        Event::without_customer(EventKind::PumpUp { station, cause }, sim_time + duration);
+    state.event_queue.push(up_event);
+    if scenario.breakdowns.queue_policy == OutagePolicy::Redirect {
+        redirect_queue(state, station, outage, scenario);
+    }
 }
 
-pub fn departure_routine(
-    event_queue: &mut EventQueue,
-    sim_time: &mut f64,
-    e: &mut Event,
-    fuel_stations: &mut [i64],
-    customer_queues: &mut Vec<Vec<Customer>>,
-    customer_data: &mut HashMap<u64, (u8, f64, f64, f64, u8)>,
+// Si la cola del surtidor se quedó sin surtidores en servicio, cada cliente
+// elige otra cola que pueda atenderlo; los que no tienen alternativa esperan.
+// Los traslados se cuentan en el corte `outage` que los provocó.
+fn redirect_queue(state: &mut SimState, station: usize, outage: usize, scenario: &Scenario) {
+    let sim_time = state.sim_time;
+    let queue = state.station_queue[station];
+    if state.queue_in_service(queue) {
+        return;
+    }
+    let customers = std::mem::take(&mut state.customer_queues[queue]);
+    for customer in customers {
+        let candidates: Vec<usize> = (0..state.customer_queues.len())
+            .filter(|&other| {
+                other != queue
+                    && state.queue_in_service(other)
+                    && state.queue_serves(other, &customer)
+            })
+            .collect();
+        if candidates.is_empty() {
+            state.customer_queues[queue].push(customer);
+            continue;
+        }
+        let context = SelectionContext {
+            customer: &customer,
+            queues: &state.customer_queues,
+            queue_stations: &state.queue_stations,
+            candidates: &candidates,
+            scenario,
+        };
+        let target = checked_queue(state.policy.as_mut(), &context, &mut state.rng.queue_ties);
+        state.stats.outage_redirected(outage);
+        enter_queue(state, customer, target);
+    }
+    let queue_length = state.customer_queues[queue].len();
+    state.stats.queue_changed(queue, sim_time, queue_length);
+}
+
+// El surtidor vuelve a servicio: toma al siguiente de su cola y, tras una
+// falla, se agenda la próxima.
+pub fn pump_up_routine(
+    state: &mut SimState,
+    station: usize,
+    cause: OutageCause,
+    scenario: &Scenario,
+    customer_data: &mut CustomerData,
 ) {
-    e.customer.total_time = *sim_time - e.customer.arrive_time;
-    let _ = update_value(
-        customer_data,
-        e.customer.id,
-        3,
-        *sim_time - e.customer.arrive_time
-    );
-    let _ = update_value(
-        customer_data,
-        e.customer.id,
-        4,
-        1.0
-    );
-    if let Some(queue) = e.chosen_queue {
-        fuel_stations[queue as usize] = 0;
-        if let Some(customer) = process_customer_queues(customer_queues, queue as usize) {
-            let refuel_event = Event::new(2, customer.clone(), *sim_time, Some(queue));
-            event_queue.add(refuel_event);
-            let _ = update_value(
-                customer_data,
-                customer.id,
-                2,
-                *sim_time - customer.arrive_time
+    let sim_time = state.sim_time;
+    state.out_of_service[station] -= 1;
+    let down = state.out_of_service[station] > 0;
+    state.stats.outage_ended(station, cause, sim_time, down);
+    if let (
+        OutageCause::Failure,
+        Failures::Random {
+            time_to_failure, ..
+        },
+    ) = (cause, scenario.breakdowns.failures)
+    {
+        let failure_event = Event::without_customer(
+            EventKind::PumpDown { station, cause },
+            sim_time + time_to_failure.sample(&mut state.rng.failures),
+        );
+        state.event_queue.push(failure_event);
+    }
+    if state.available(station) {
+        if start_from_queue(state, station) {
+            state.stats.pump_busy(station, sim_time);
+            state.update_entry_blocking(station);
+        } else {
+            jockey(state, station, scenario, customer_data);
+        }
+    }
+}
+
+// Ajusta los bomberos de turno para el tramo que empieza ahora y agenda el
+// próximo cambio.
+pub fn schedule_staffing(state: &mut SimState, scenario: &Scenario) {
+    if !scenario.attendants.limited() {
+        return;
+    }
+    let sim_time = state.sim_time;
+    let next_change = scenario
+        .attendants
+        .hours_to_next_change(clock_hours(sim_time))
+        * 60.0;
+    // Se evalúa a mitad del tramo para no depender del redondeo en los bordes.
+    let on_duty = scenario
+        .attendants
+        .on_duty(clock_hours(sim_time + next_change / 2.0));
+    state.attendants.set_on_duty(on_duty, sim_time);
+    let staff_event = Event::without_customer(EventKind::StaffChange, sim_time + next_change);
+    state.event_queue.push(staff_event);
+}
+
+pub fn staff_change_routine(
+    state: &mut SimState,
+    scenario: &Scenario,
+    customer_data: &mut CustomerData,
+) {
+    schedule_staffing(state, scenario);
+    dispatch_attendants(state, scenario, customer_data);
+}
+
+#[cfg(test)]
+mod tests {
+    use super::{
+        departure_routine, jockey, normal_duration, normalize, payment_routine, pump_down_routine,
+        queue_routine, refuel_routine, rejoin_routine, start_from_queue,
+    };
+    use crate::customer::Vehicle;
+    use crate::event::OutageCause;
+    use crate::scenario::{
+        Balking, Breakdowns, DurationModel, Failures, Jockeying, MaintenanceWindow, OutagePolicy,
+        QueueTopology, Shift, Staffing,
+    };
+    use crate::{
+        Customer, CustomerData, CustomerRecord, Event, EventKind, Fuel, QueueSelection,
+        QueueSelectionPolicy, Scenario, SelectionContext, SimState,
+    };
+    use rand::rngs::StdRng;
+    use rand::SeedableRng;
+    use std::sync::atomic::{AtomicUsize, Ordering};
+    use std::sync::Arc;
+
+    #[test]
+    fn normalize_repeats_every_day() {
+        assert_eq!(normalize(0.0), 0.0);
+        assert_eq!(normalize(720.0), 0.5);
+        for day in [1.0, 45.0, 46.0, 365.0] {
+            let minutes = day * 1440.0 + 612.5;
+            assert!((normalize(minutes) - normalize(612.5)).abs() < 1e-9);
+        }
+    }
+
+    #[test]
+    fn normal_durations_are_never_negative() {
+        let mut rng = StdRng::seed_from_u64(1);
+        let durations: Vec<f64> = (0..10_000)
+            .map(|_| normal_duration(0.425, 5.0, &mut rng))
+            .collect();
+        assert!(durations.iter().all(|&duration| duration >= 0.0));
+        assert!(durations.contains(&0.0));
+    }
+
+    // Si la paciencia se agota durante el cambio de cola, el abandono queda
+    // en ese instante y no en la llegada a la nueva cola.
+    #[test]
+    fn patience_running_out_in_transit_dates_the_abandonment() {
+        let scenario = Scenario::default();
+        let mut state = SimState::new(scenario.stations, &scenario, 1);
+        let mut customer = Customer::new(0, 1.0, &scenario, &mut state.rng);
+        customer.patience = Some(2.0);
+        let mut record = CustomerRecord::new(&customer, 1.0);
+        record.queue_entry = Some(1.0);
+        let mut customer_data = CustomerData::new();
+        customer_data.insert(0, record);
+
+        state.sim_time = 4.5;
+        let mut e = Event::new(EventKind::Rejoin { queue: 0 }, customer, 4.5);
+        rejoin_routine(&mut state, &mut e, 0, &scenario, &mut customer_data);
+        let record = &customer_data[&0];
+        assert!(record.reneged);
+        assert_eq!(record.departure, Some(3.0));
+        assert_eq!(record.total_time(), Some(2.0));
+        assert!(state.customer_queues[0].is_empty());
+    }
+
+    // Llega un cliente de 93 con todos los surtidores ocupados; devuelve la
+    // cola en que quedó.
+    fn queue_with_pumps_busy(stations: usize, topology: QueueTopology) -> (SimState, usize) {
+        let scenario = Scenario {
+            queues: topology,
+            ..Scenario::default()
+        };
+        let mut state = SimState::new(stations, &scenario, 1);
+        state.fuel_stations = vec![1; stations];
+        let mut customer = Customer::new(0, 0.0, &scenario, &mut state.rng);
+        customer.fuel = Fuel::Gasolina93;
+        customer.vehicle = Vehicle::Car;
+        customer.patience = None;
+        let mut customer_data = CustomerData::new();
+        customer_data.insert(0, CustomerRecord::new(&customer, 0.0));
+        let mut e = Event::new(EventKind::Queue, customer, 0.0);
+        queue_routine(&mut state, &mut e, &scenario, &mut customer_data);
+        let queue = (0..state.customer_queues.len())
+            .find(|&queue| !state.customer_queues[queue].is_empty())
+            .unwrap();
+        (state, queue)
+    }
+
+    #[test]
+    fn shared_queue_feeds_any_pump() {
+        let (mut state, queue) = queue_with_pumps_busy(4, QueueTopology::Shared);
+        assert_eq!(state.customer_queues.len(), 1);
+        assert_eq!(queue, 0);
+        state.fuel_stations[3] = 0;
+        assert!(start_from_queue(&mut state, 3));
+        assert!(state.customer_queues[0].is_empty());
+        let refuel = state.event_queue.pop().unwrap();
+        assert_eq!(refuel.kind, EventKind::Refuel { station: 3 });
+    }
+
+    #[test]
+    fn island_queue_feeds_only_its_island() {
+        let islands = QueueTopology::Islands {
+            pumps_per_island: 2,
+        };
+        let (mut state, queue) = queue_with_pumps_busy(4, islands);
+        assert_eq!(state.customer_queues.len(), 2);
+        assert_eq!(state.queue_stations[queue], vec![2 * queue, 2 * queue + 1]);
+        // Un surtidor de la otra isla no lo atiende.
+        let other = 2 * (1 - queue);
+        state.fuel_stations[other] = 0;
+        assert!(!start_from_queue(&mut state, other));
+        assert_eq!(state.customer_queues[queue].len(), 1);
+        let own = 2 * queue + 1;
+        state.fuel_stations[own] = 0;
+        assert!(start_from_queue(&mut state, own));
+        assert!(state.customer_queues[queue].is_empty());
+    }
+
+    // Un solo bombero y dos clientes en surtidores distintos: el segundo espera
+    // en el surtidor hasta que el bombero se libera, al terminar la carga si no
+    // cobra o al terminar el cobro si cobra.
+    #[test]
+    fn refuel_waits_for_a_free_attendant() {
+        for takes_payment in [false, true] {
+            let scenario = Scenario {
+                attendants: Staffing::Shifts {
+                    shifts: vec![Shift {
+                        start: 0.0,
+                        end: 24.0,
+                        attendants: 1,
+                        breaks: Vec::new(),
+                    }],
+                    takes_payment,
+                },
+                ..Scenario::default()
+            };
+            let mut state = SimState::new(2, &scenario, 1);
+            state.attendants.set_on_duty(1, 0.0);
+            let mut customer_data = CustomerData::new();
+            let mut events = Vec::new();
+            for (id, station) in [(0, 0), (1, 1)] {
+                let customer = Customer::new(id, 0.0, &scenario, &mut state.rng);
+                customer_data.insert(id, CustomerRecord::new(&customer, 0.0));
+                state.fuel_stations[station] = 1;
+                let mut e = Event::new(EventKind::Refuel { station }, customer, 0.0);
+                refuel_routine(&mut state, &mut e, station, &scenario, &mut customer_data);
+                events.push(e);
+            }
+            assert_eq!(customer_data[&0].service_start, Some(0.0));
+            assert_eq!(customer_data[&1].service_start, None);
+            assert_eq!(state.attendants.busy, 1);
+
+            state.sim_time = 2.0;
+            let mut payment = Event::new(
+                EventKind::Payment { station: 0 },
+                events[0].customer.clone(),
+                2.0,
             );
+            payment_routine(&mut state, &mut payment, 0, &scenario, &mut customer_data);
+            if takes_payment {
+                assert_eq!(customer_data[&1].service_start, None);
+                state.sim_time = 3.0;
+                let mut departure = Event::new(
+                    EventKind::Departure { station: 0 },
+                    events[0].customer.clone(),
+                    3.0,
+                );
+                departure_routine(&mut state, &mut departure, 0, &scenario, &mut customer_data);
+            }
+            let released = if takes_payment { 3.0 } else { 2.0 };
+            assert_eq!(customer_data[&1].service_start, Some(released));
+            assert_eq!(customer_data[&1].attendant_wait, released);
+            assert_eq!(state.attendants.busy, 1);
         }
     }
-    // i need to update the last value of customer_data to 1
 
-    /* println!(
-        "Customer {} que pagó con {:?} terminó después de {:.2} segs.",
-        e.customer.id,
-        e.customer.payment_method,
-        e.customer.total_time
-    ); */
-}
\ No newline at end of file
+    // Cuenta las veces que se le pide una cola.
+    struct CountingPolicy(Arc<AtomicUsize>);
+
+    impl QueueSelectionPolicy for CountingPolicy {
+        fn choose_pump(
+            &mut self,
+            free: &[usize],
+            _context: &SelectionContext,
+            _rng: &mut StdRng,
+        ) -> usize {
+            free[0]
+        }
+
+        fn choose_queue(&mut self, context: &SelectionContext, _rng: &mut StdRng) -> usize {
+            self.0.fetch_add(1, Ordering::SeqCst);
+            context.candidates[0]
+        }
+    }
+
+    // Quien hace balking no consulta la política, así que no avanza su estado
+    // ni el sorteo de empates que usan los que sí entran a la cola.
+    #[test]
+    fn balking_customers_do_not_consult_the_policy() {
+        for (balking, calls) in [(Balking::Threshold { length: 0 }, 0), (Balking::None, 1)] {
+            let chosen = Arc::new(AtomicUsize::new(0));
+            let counter = chosen.clone();
+            let scenario = Scenario {
+                balking,
+                queue_selection: QueueSelection::custom(move || {
+                    Box::new(CountingPolicy(counter.clone()))
+                }),
+                ..Scenario::default()
+            };
+            let mut state = SimState::new(2, &scenario, 1);
+            state.fuel_stations = vec![1; 2];
+            let customer = Customer::new(0, 0.0, &scenario, &mut state.rng);
+            let mut customer_data = CustomerData::new();
+            customer_data.insert(0, CustomerRecord::new(&customer, 0.0));
+            let mut e = Event::new(EventKind::Queue, customer, 0.0);
+            queue_routine(&mut state, &mut e, &scenario, &mut customer_data);
+            assert_eq!(chosen.load(Ordering::SeqCst), calls);
+            assert_eq!(customer_data[&0].balked, calls == 0);
+        }
+    }
+
+    // Con `difference` 0 basta que la otra cola sea tan larga como la propia;
+    // con ambas vacías no hay nadie que cambiar.
+    #[test]
+    fn jockeying_with_zero_difference() {
+        let scenario = Scenario {
+            jockeying: Jockeying::ShorterQueue {
+                difference: 0,
+                delay: 0.5,
+            },
+            ..Scenario::default()
+        };
+        let mut state = SimState::new(2, &scenario, 1);
+        let mut customer_data = CustomerData::new();
+        jockey(&mut state, 0, &scenario, &mut customer_data);
+        assert!(state.event_queue.pop().is_none());
+
+        let customer = Customer::new(0, 0.0, &scenario, &mut state.rng);
+        customer_data.insert(0, CustomerRecord::new(&customer, 0.0));
+        state.customer_queues[1].push(customer);
+        jockey(&mut state, 0, &scenario, &mut customer_data);
+        assert!(state.customer_queues[1].is_empty());
+        assert_eq!(customer_data[&0].jockeys, 1);
+        let rejoin = state.event_queue.pop().unwrap();
+        assert_eq!(rejoin.kind, EventKind::Rejoin { queue: 0 });
+        assert_eq!(rejoin.scheduled_time, 0.5);
+    }
+
+    // Una falla y una mantención del mismo surtidor se superponen: cada corte
+    // cuenta solo a los clientes que trasladó él.
+    #[test]
+    fn overlapping_outages_count_their_own_redirects() {
+        let scenario = Scenario {
+            breakdowns: Breakdowns {
+                failures: Failures::Random {
+                    time_to_failure: DurationModel::Fixed { minutes: 1000.0 },
+                    repair_time: DurationModel::Fixed { minutes: 30.0 },
+                },
+                maintenance: vec![MaintenanceWindow {
+                    pump: 0,
+                    start: 1.0,
+                    duration: 60.0,
+                    interval: None,
+                }],
+                queue_policy: OutagePolicy::Redirect,
+            },
+            ..Scenario::default()
+        };
+        let mut state = SimState::new(3, &scenario, 1);
+        state.fuel_stations = vec![1; 3];
+        let push = |state: &mut SimState, id: u64| {
+            let mut customer = Customer::new(id, 0.0, &scenario, &mut state.rng);
+            customer.vehicle = Vehicle::Car;
+            state.customer_queues[0].push(customer);
+        };
+        push(&mut state, 0);
+        push(&mut state, 1);
+        state.sim_time = 1.0;
+        let maintenance = OutageCause::Maintenance { window: 0 };
+        pump_down_routine(&mut state, 0, maintenance, &scenario);
+        assert!(state.customer_queues[0].is_empty());
+
+        // Durante la mantención alguien queda en la cola 0 y el surtidor falla.
+        push(&mut state, 2);
+        state.sim_time = 2.0;
+        pump_down_routine(&mut state, 0, OutageCause::Failure, &scenario);
+        assert!(state.customer_queues[0].is_empty());
+
+        let outages = &state.stats.outages;
+        assert_eq!(outages.len(), 2);
+        assert_eq!((outages[0].cause, outages[0].redirected), (maintenance, 2));
+        assert_eq!(
+            (outages[1].cause, outages[1].redirected),
+            (OutageCause::Failure, 1)
+        );
+    }
+}
@@ -0,0 +1,1383 @@
+use crate::customer::{Fuel, Vehicle};
+use crate::selection::QueueSelection;
+use crate::PaymentMethod;
+
+use rand::distributions::WeightedIndex;
+use rand::Rng;
+use rand_distr::{Distribution, Exp, Normal, Uniform};
+use serde::Deserialize;
+use std::fs;
+
+// Parámetros de un escenario de la estación. Los valores por defecto
+// reproducen el modelo original; un archivo TOML solo necesita indicar
+// los campos que cambian.
+#[derive(Clone, Debug, Deserialize)]
+#[serde(default, deny_unknown_fields)]
+pub struct Scenario {
+    pub stations: usize,
+    // Cantidad de estaciones del escenario alternativo en el gráfico comparativo.
+    pub comparison_stations: usize,
+    // Tiempo de carga cuando no se modelan los litros (`dispensing.litres`).
+    pub refuel: NormalParams,
+    pub dispensing: Dispensing,
+    pub payment: PaymentScenario,
+    pub fuel: FuelScenario,
+    pub inventory: InventoryScenario,
+    pub vehicles: VehicleScenario,
+    pub arrivals: ArrivalCurve,
+    pub balking: Balking,
+    pub reneging: Reneging,
+    pub jockeying: Jockeying,
+    pub queues: QueueTopology,
+    pub layout: Layout,
+    pub breakdowns: Breakdowns,
+    pub attendants: Staffing,
+    pub cashier: Cashier,
+    // Cómo eligen surtidor y cola los clientes.
+    pub queue_selection: QueueSelection,
+}
+
+#[derive(Clone, Copy, Debug, Deserialize)]
+#[serde(deny_unknown_fields)]
+pub struct NormalParams {
+    pub mean: f64,
+    pub std_dev: f64,
+}
+
+#[derive(Clone, Debug, Deserialize)]
+#[serde(default, deny_unknown_fields)]
+pub struct PaymentScenario {
+    // Multiplica la media de todos los tiempos de pago.
+    pub factor: f64,
+    pub efectivo: PaymentParams,
+    pub tarjeta: PaymentParams,
+    pub copec_app: PaymentParams,
+}
+
+#[derive(Clone, Copy, Debug, Deserialize)]
+#[serde(deny_unknown_fields)]
+pub struct PaymentParams {
+    // Peso relativo del método en la mezcla de clientes.
+    pub share: f64,
+    pub mean: f64,
+    pub std_dev: f64,
+}
+
+// Carga según litros: tiempo = overhead + litros / caudal del surtidor.
+#[derive(Clone, Debug, Deserialize)]
+#[serde(default, deny_unknown_fields)]
+pub struct Dispensing {
+    pub litres: LitresModel,
+    // Caudal en litros por minuto de los surtidores que no aparecen en
+    // `pump_flow_rates`.
+    pub flow_rate: f64,
+    pub pump_flow_rates: Vec<f64>,
+    // Minutos fijos de manejo de la pistola, tapa, etc.
+    pub overhead: f64,
+}
+
+// Litros que carga cada cliente. Con `none` el tiempo de carga se sortea de
+// `refuel` como en el modelo original.
+#[derive(Clone, Copy, Debug, Default, Deserialize)]
+#[serde(tag = "model", rename_all = "snake_case", deny_unknown_fields)]
+pub enum LitresModel {
+    #[default]
+    None,
+    // Llena el estanque: capacidad * (1 - nivel), con el nivel uniforme.
+    Tank {
+        capacity: NormalParams,
+        fill_level: Range,
+    },
+    Requested {
+        litres: NormalParams,
+    },
+}
+
+#[derive(Clone, Copy, Debug, Deserialize)]
+#[serde(deny_unknown_fields)]
+pub struct Range {
+    pub min: f64,
+    pub max: f64,
+}
+
+#[derive(Clone, Debug, Default, Deserialize)]
+#[serde(default, deny_unknown_fields)]
+pub struct FuelScenario {
+    pub mix: FuelMix,
+    // Combustibles que ofrece cada surtidor, en orden. Los surtidores que no
+    // aparecen ofrecen todos.
+    pub pumps: Vec<Vec<Fuel>>,
+}
+
+// Peso relativo de cada combustible en la mezcla de clientes.
+#[derive(Clone, Copy, Debug, Deserialize)]
+#[serde(default, deny_unknown_fields)]
+pub struct FuelMix {
+    #[serde(rename = "93")]
+    pub gasolina_93: f64,
+    #[serde(rename = "95")]
+    pub gasolina_95: f64,
+    #[serde(rename = "97")]
+    pub gasolina_97: f64,
+    pub diesel: f64,
+}
+
+// Estanques por combustible y su reposición. Los combustibles sin estanque
+// nunca se agotan.
+#[derive(Clone, Debug, Default, Deserialize)]
+#[serde(default, deny_unknown_fields)]
+pub struct InventoryScenario {
+    pub tanks: FuelTanks,
+    pub resupply: Resupply,
+    // Minutos que el combustible queda cerrado mientras descarga el camión.
+    pub unloading_time: f64,
+}
+
+#[derive(Clone, Copy, Debug, Default, Deserialize)]
+#[serde(default, deny_unknown_fields)]
+pub struct FuelTanks {
+    #[serde(rename = "93")]
+    pub gasolina_93: Option<TankParams>,
+    #[serde(rename = "95")]
+    pub gasolina_95: Option<TankParams>,
+    #[serde(rename = "97")]
+    pub gasolina_97: Option<TankParams>,
+    pub diesel: Option<TankParams>,
+}
+
+#[derive(Clone, Copy, Debug, Deserialize)]
+#[serde(deny_unknown_fields)]
+pub struct TankParams {
+    pub capacity: f64,
+    // Litros al inicio; por defecto el estanque parte lleno.
+    pub initial: Option<f64>,
+}
+
+// Cuándo llega el camión cisterna.
+#[derive(Clone, Copy, Debug, Default, Deserialize)]
+#[serde(tag = "model", rename_all = "snake_case", deny_unknown_fields)]
+pub enum Resupply {
+    #[default]
+    None,
+    // Política (s, Q): al quedar en `reorder_level` litros o menos se piden
+    // `order_quantity` litros, que llegan `lead_time` minutos después.
+    ReorderPoint {
+        reorder_level: f64,
+        order_quantity: f64,
+        lead_time: f64,
+    },
+    // Un camión cada `interval` minutos a partir de `first`, que llena el
+    // estanque.
+    Scheduled {
+        first: f64,
+        interval: f64,
+    },
+}
+
+// Categorías de vehículo. Por defecto todos los clientes son autos.
+#[derive(Clone, Debug, Deserialize)]
+#[serde(default, deny_unknown_fields)]
+pub struct VehicleScenario {
+    pub car: VehicleParams,
+    pub motorcycle: VehicleParams,
+    pub van: VehicleParams,
+    pub truck: VehicleParams,
+}
+
+// Lo que no se indica para una categoría se toma de la configuración general.
+#[derive(Clone, Debug, Default, Deserialize)]
+#[serde(default, deny_unknown_fields)]
+pub struct VehicleParams {
+    // Peso relativo de la categoría en la mezcla de llegadas.
+    pub share: f64,
+    // Mezcla de métodos de pago propia de la categoría.
+    pub payment: Option<PaymentShares>,
+    // Combustibles que puede cargar; se sortean con los pesos de `fuel.mix`.
+    pub fuels: Vec<Fuel>,
+    pub litres: Option<LitresModel>,
+    // Surtidores que admiten la categoría (todos si está vacío) y caudal
+    // mínimo exigido.
+    pub pumps: Vec<usize>,
+    pub min_flow_rate: f64,
+}
+
+#[derive(Clone, Copy, Debug, Default, Deserialize)]
+#[serde(default, deny_unknown_fields)]
+pub struct PaymentShares {
+    pub efectivo: f64,
+    pub tarjeta: f64,
+    pub copec_app: f64,
+}
+
+// Disposición física de los surtidores. En `islands` cada lado de una isla es
+// una pista con `positions_per_side` surtidores en fila; la posición 0 es la
+// de adelante (hacia la salida). Un auto terminado no puede salir mientras
+// haya otro adelante, y no se puede llegar a una posición con otro auto
+// detrás. Los surtidores se numeran isla por isla, lado por lado y de
+// adelante hacia atrás.
+#[derive(Clone, Copy, Debug, Default, Deserialize)]
+#[serde(tag = "model", rename_all = "snake_case", deny_unknown_fields)]
+pub enum Layout {
+    // Cada surtidor tiene su propia pista, sin bloqueos.
+    #[default]
+    Independent,
+    Islands {
+        positions_per_side: usize,
+        sides: usize,
+    },
+}
+
+impl Layout {
+    // Surtidores de cada pista, de adelante hacia atrás.
+    pub fn lanes(&self, stations: usize) -> Vec<Vec<usize>> {
+        let positions = match self {
+            Layout::Independent => 1,
+            Layout::Islands {
+                positions_per_side, ..
+            } => *positions_per_side,
+        };
+        (0..stations)
+            .collect::<Vec<usize>>()
+            .chunks(positions.max(1))
+            .map(|lane| lane.to_vec())
+            .collect()
+    }
+
+    pub fn sides(&self) -> usize {
+        match self {
+            Layout::Independent => 1,
+            Layout::Islands { sides, .. } => *sides,
+        }
+    }
+
+    // Surtidores por isla, numerados en forma consecutiva.
+    pub fn pumps_per_island(&self) -> Option<usize> {
+        match self {
+            Layout::Independent => None,
+            Layout::Islands {
+                positions_per_side,
+                sides,
+            } => Some(positions_per_side * sides),
+        }
+    }
+}
+
+// Fallas y mantenciones de los surtidores. Un surtidor fuera de servicio no
+// recibe clientes nuevos; el que estaba cargando termina su atención.
+#[derive(Clone, Debug, Default, Deserialize)]
+#[serde(default, deny_unknown_fields)]
+pub struct Breakdowns {
+    pub failures: Failures,
+    pub maintenance: Vec<MaintenanceWindow>,
+    // Qué pasa con la cola de un surtidor que sale de servicio.
+    pub queue_policy: OutagePolicy,
+}
+
+// Cada surtidor falla `time_to_failure` minutos de reloj después de volver al
+// servicio (o del inicio), esté cargando u ocioso, y vuelve tras `repair_time`.
+#[derive(Clone, Copy, Debug, Default, Deserialize)]
+#[serde(tag = "model", rename_all = "snake_case", deny_unknown_fields)]
+pub enum Failures {
+    #[default]
+    None,
+    Random {
+        time_to_failure: DurationModel,
+        repair_time: DurationModel,
+    },
+}
+
+#[derive(Clone, Copy, Debug, Deserialize)]
+#[serde(tag = "model", rename_all = "snake_case", deny_unknown_fields)]
+pub enum DurationModel {
+    Fixed { minutes: f64 },
+    Exponential { mean: f64 },
+    // Truncada en 0.
+    Normal { mean: f64, std_dev: f64 },
+}
+
+// Mantención programada del surtidor `pump`: parte en el minuto `start` de la
+// simulación, dura `duration` y se repite cada `interval` minutos si se indica.
+// Se ignora si el surtidor no existe en la configuración simulada.
+#[derive(Clone, Copy, Debug, Deserialize)]
+#[serde(deny_unknown_fields)]
+pub struct MaintenanceWindow {
+    pub pump: usize,
+    pub start: f64,
+    pub duration: f64,
+    pub interval: Option<f64>,
+}
+
+#[derive(Clone, Copy, Debug, Default, Deserialize, PartialEq)]
+#[serde(rename_all = "snake_case")]
+pub enum OutagePolicy {
+    // Los clientes se quedan en la cola esperando la reparación.
+    #[default]
+    Strand,
+    // Los clientes pasan a otra cola, elegida con la política de selección.
+    Redirect,
+}
+
+// Bomberos que operan los surtidores. Cada carga necesita un bombero libre,
+// que queda tomado hasta terminar la carga o, con `takes_payment`, hasta
+// cobrar. Con `unlimited` (por defecto) no limitan la atención.
+#[derive(Clone, Debug, Default, Deserialize)]
+#[serde(tag = "model", rename_all = "snake_case", deny_unknown_fields)]
+pub enum Staffing {
+    #[default]
+    Unlimited,
+    Shifts {
+        shifts: Vec<Shift>,
+        #[serde(default = "default_takes_payment")]
+        takes_payment: bool,
+    },
+}
+
+fn default_takes_payment() -> bool {
+    true
+}
+
+// Turno diario de `attendants` bomberos entre las horas `start` y `end` del
+// reloj (puede cruzar la medianoche).
+#[derive(Clone, Debug, Deserialize)]
+#[serde(deny_unknown_fields)]
+pub struct Shift {
+    pub start: f64,
+    pub end: f64,
+    pub attendants: usize,
+    #[serde(default)]
+    pub breaks: Vec<ShiftBreak>,
+}
+
+// Colación: `attendants` bomberos del turno salen a la hora `at` por
+// `minutes` minutos.
+#[derive(Clone, Copy, Debug, Deserialize)]
+#[serde(deny_unknown_fields)]
+pub struct ShiftBreak {
+    pub at: f64,
+    pub minutes: f64,
+    pub attendants: usize,
+}
+
+// Caja central: los clientes que pagan con alguno de `methods` caminan a la
+// tienda (`walk_time` minutos ida y vuelta) y hacen fila para uno de los
+// `cashiers` cajeros, mientras su auto sigue en el surtidor. Los demás pagan
+// en el surtidor. El tiempo de cobro es el mismo de `payment`.
+#[derive(Clone, Debug, Deserialize)]
+#[serde(default, deny_unknown_fields)]
+pub struct Cashier {
+    pub methods: Vec<PaymentMethod>,
+    pub cashiers: usize,
+    pub walk_time: f64,
+}
+
+impl Default for Cashier {
+    fn default() -> Self {
+        Cashier {
+            methods: Vec::new(),
+            cashiers: 1,
+            walk_time: 1.0,
+        }
+    }
+}
+
+impl Cashier {
+    pub fn enabled(&self) -> bool {
+        !self.methods.is_empty()
+    }
+
+    pub fn pays_at_counter(&self, payment_method: PaymentMethod) -> bool {
+        self.methods.contains(&payment_method)
+    }
+
+    fn validate(&self) -> Result<(), String> {
+        if self.cashiers == 0 || !self.walk_time.is_finite() || self.walk_time < 0.0 {
+            return Err(String::from(
+                "cashier.cashiers debe ser al menos 1 y cashier.walk_time no negativo",
+            ));
+        }
+        Ok(())
+    }
+}
+
+// Modelo de balking: si no hay surtidor libre, el cliente puede irse sin
+// entrar a la cola según el largo de la cola más corta.
+#[derive(Clone, Debug, Default, Deserialize)]
+#[serde(tag = "model", rename_all = "snake_case", deny_unknown_fields)]
+pub enum Balking {
+    #[default]
+    None,
+    // Se va siempre que la cola más corta tenga al menos `length` clientes.
+    Threshold {
+        length: usize,
+    },
+    // Probabilidad de irse según el largo de la cola más corta: el elemento i
+    // aplica a largo i, y el último a todos los largos mayores.
+    Probability {
+        by_length: Vec<f64>,
+    },
+}
+
+impl Balking {
+    pub fn probability(&self, shortest_queue: usize) -> f64 {
+        match self {
+            Balking::None => 0.0,
+            Balking::Threshold { length } => {
+                if shortest_queue >= *length {
+                    1.0
+                } else {
+                    0.0
+                }
+            }
+            Balking::Probability { by_length } => match by_length.last() {
+                Some(last) => *by_length.get(shortest_queue).unwrap_or(last),
+                None => 0.0,
+            },
+        }
+    }
+}
+
+// Modelo de reneging: cada cliente tiene una paciencia y abandona la cola si
+// no llega a un surtidor antes de que se agote.
+#[derive(Clone, Copy, Debug, Default, Deserialize)]
+#[serde(tag = "model", rename_all = "snake_case", deny_unknown_fields)]
+pub enum Reneging {
+    #[default]
+    None,
+    Fixed {
+        minutes: f64,
+    },
+    Exponential {
+        mean: f64,
+    },
+}
+
+impl Reneging {
+    // Paciencia en minutos; None si el cliente espera indefinidamente.
+    pub fn sample_patience<R: Rng>(&self, rng: &mut R) -> Option<f64> {
+        match self {
+            Reneging::None => None,
+            Reneging::Fixed { minutes } => Some(*minutes),
+            Reneging::Exponential { mean } => Some(Exp::new(1.0 / mean).unwrap().sample(rng)),
+        }
+    }
+}
+
+// Modelo de jockeying: al liberarse un surtidor, el último cliente de la cola
+// más larga se cambia a la cola de ese surtidor si esta es al menos
+// `difference` clientes más corta. El cambio toma `delay` minutos.
+#[derive(Clone, Copy, Debug, Default, Deserialize)]
+#[serde(tag = "model", rename_all = "snake_case", deny_unknown_fields)]
+pub enum Jockeying {
+    #[default]
+    None,
+    ShorterQueue {
+        difference: usize,
+        delay: f64,
+    },
+}
+
+// Disposición de las colas: una por surtidor, una sola fila que alimenta a
+// todos, o una por isla de `pumps_per_island` surtidores consecutivos.
+#[derive(Clone, Copy, Debug, Default, Deserialize)]
+#[serde(tag = "topology", rename_all = "snake_case", deny_unknown_fields)]
+pub enum QueueTopology {
+    #[default]
+    PerPump,
+    Shared,
+    Islands {
+        pumps_per_island: usize,
+    },
+}
+
+impl QueueTopology {
+    // Surtidores que atiende cada cola, para una estación con `stations` surtidores.
+    pub fn queue_stations(&self, stations: usize) -> Vec<Vec<usize>> {
+        let group_size = match self {
+            QueueTopology::PerPump => 1,
+            QueueTopology::Shared => stations,
+            QueueTopology::Islands { pumps_per_island } => *pumps_per_island,
+        };
+        (0..stations)
+            .collect::<Vec<usize>>()
+            .chunks(group_size.max(1))
+            .map(|group| group.to_vec())
+            .collect()
+    }
+}
+
+// Curva de llegadas a lo largo del día: una densidad beta escalada,
+// x^alpha * (1 - x)^beta / normalizer + baseline, con x la fracción del día.
+#[derive(Clone, Copy, Debug, Deserialize)]
+#[serde(default, deny_unknown_fields)]
+pub struct ArrivalCurve {
+    pub alpha: f64,
+    pub beta: f64,
+    pub normalizer: f64,
+    pub baseline: f64,
+    // La tasa del proceso exponencial es scale / curva.
+    pub scale: f64,
+}
+
+impl Default for Scenario {
+    fn default() -> Self {
+        Scenario {
+            stations: 4,
+            comparison_stations: 5,
+            refuel: NormalParams {
+                mean: 2.0,
+                std_dev: 0.15,
+            },
+            dispensing: Dispensing::default(),
+            payment: PaymentScenario::default(),
+            fuel: FuelScenario::default(),
+            inventory: InventoryScenario::default(),
+            vehicles: VehicleScenario::default(),
+            arrivals: ArrivalCurve::default(),
+            balking: Balking::None,
+            reneging: Reneging::None,
+            jockeying: Jockeying::None,
+            queues: QueueTopology::PerPump,
+            layout: Layout::Independent,
+            breakdowns: Breakdowns::default(),
+            attendants: Staffing::Unlimited,
+            cashier: Cashier::default(),
+            queue_selection: QueueSelection::Shortest,
+        }
+    }
+}
+
+impl Default for PaymentScenario {
+    fn default() -> Self {
+        PaymentScenario {
+            factor: 1.0,
+            efectivo: PaymentParams {
+                share: 1.0,
+                mean: 0.875,
+                std_dev: 0.1,
+            },
+            tarjeta: PaymentParams {
+                share: 1.0,
+                mean: 0.425,
+                std_dev: 0.075,
+            },
+            copec_app: PaymentParams {
+                share: 1.0,
+                mean: 0.275,
+                std_dev: 0.055,
+            },
+        }
+    }
+}
+
+impl Default for Dispensing {
+    fn default() -> Self {
+        Dispensing {
+            litres: LitresModel::None,
+            flow_rate: 35.0,
+            pump_flow_rates: Vec::new(),
+            overhead: 0.75,
+        }
+    }
+}
+
+impl Default for VehicleScenario {
+    fn default() -> Self {
+        VehicleScenario {
+            car: VehicleParams {
+                share: 1.0,
+                ..VehicleParams::default()
+            },
+            motorcycle: VehicleParams::default(),
+            van: VehicleParams::default(),
+            truck: VehicleParams::default(),
+        }
+    }
+}
+
+impl Default for FuelMix {
+    fn default() -> Self {
+        FuelMix {
+            gasolina_93: 0.45,
+            gasolina_95: 0.25,
+            gasolina_97: 0.1,
+            diesel: 0.2,
+        }
+    }
+}
+
+impl Default for ArrivalCurve {
+    fn default() -> Self {
+        ArrivalCurve {
+            alpha: 7.0,
+            beta: 5.075,
+            normalizer: 0.0000908345394559,
+            baseline: 0.1,
+            scale: 10.0,
+        }
+    }
+}
+
+impl Scenario {
+    pub fn from_file(path: &str) -> Result<Scenario, String> {
+        let contents = fs::read_to_string(path)
+            .map_err(|err| format!("No se pudo leer el escenario {}: {}", path, err))?;
+        let scenario: Scenario = toml::from_str(&contents)
+            .map_err(|err| format!("Escenario inválido en {}: {}", path, err))?;
+        scenario.validate()?;
+        Ok(scenario)
+    }
+
+    pub fn validate(&self) -> Result<(), String> {
+        if self.stations == 0 || self.comparison_stations == 0 {
+            return Err(String::from(
+                "stations y comparison_stations deben ser al menos 1",
+            ));
+        }
+        self.refuel.validate("refuel")?;
+        self.dispensing.validate()?;
+        if !is_positive(self.payment.factor) {
+            return Err(String::from("payment.factor debe ser positivo"));
+        }
+        for (name, params) in self.payment.methods() {
+            NormalParams {
+                mean: params.mean,
+                std_dev: params.std_dev,
+            }
+            .validate(name)?;
+            if params.share.is_nan() || params.share < 0.0 {
+                return Err(format!("{}.share no puede ser negativo", name));
+            }
+        }
+        if WeightedIndex::new(self.payment.shares()).is_err() {
+            return Err(String::from("la suma de payment.*.share debe ser positiva"));
+        }
+        let fuel_shares = self.fuel.mix.shares();
+        if fuel_shares
+            .iter()
+            .any(|share| share.is_nan() || *share < 0.0)
+            || WeightedIndex::new(fuel_shares).is_err()
+        {
+            return Err(String::from(
+                "fuel.mix no puede tener pesos negativos y su suma debe ser positiva",
+            ));
+        }
+        if self.fuel.pumps.iter().any(|fuels| fuels.is_empty()) {
+            return Err(String::from(
+                "cada surtidor de fuel.pumps debe ofrecer al menos un combustible",
+            ));
+        }
+        self.vehicles.validate(self)?;
+        self.validate_stations(self.stations)?;
+        self.validate_stations(self.comparison_stations)?;
+        self.inventory.validate(self)?;
+        self.breakdowns.validate()?;
+        self.attendants.validate()?;
+        self.cashier.validate()?;
+        if let Layout::Islands {
+            positions_per_side,
+            sides,
+        } = self.layout
+        {
+            if positions_per_side == 0 || sides == 0 {
+                return Err(String::from(
+                    "layout.positions_per_side y layout.sides deben ser al menos 1",
+                ));
+            }
+        }
+        if !is_positive(self.arrivals.normalizer) || !is_positive(self.arrivals.scale) {
+            return Err(String::from(
+                "arrivals.normalizer y arrivals.scale deben ser positivos",
+            ));
+        }
+        if let Balking::Probability { by_length } = &self.balking {
+            if by_length.iter().any(|p| !(0.0..=1.0).contains(p)) {
+                return Err(String::from(
+                    "balking.by_length debe contener probabilidades entre 0 y 1",
+                ));
+            }
+        }
+        match self.reneging {
+            Reneging::Fixed { minutes: value } | Reneging::Exponential { mean: value }
+                if !is_positive(value) =>
+            {
+                return Err(String::from("la paciencia de reneging debe ser positiva"));
+            }
+            _ => {}
+        }
+        if let Jockeying::ShorterQueue { difference, delay } = self.jockeying {
+            if difference == 0 || delay.is_nan() || delay < 0.0 {
+                return Err(String::from(
+                    "jockeying.difference debe ser al menos 1 y jockeying.delay no negativo",
+                ));
+            }
+        }
+        if let QueueTopology::Islands {
+            pumps_per_island: 0,
+        } = self.queues
+        {
+            return Err(String::from("queues.pumps_per_island debe ser al menos 1"));
+        }
+        // Las colas por isla deben agrupar las mismas islas del layout.
+        if let (QueueTopology::Islands { pumps_per_island }, Some(layout_pumps)) =
+            (self.queues, self.layout.pumps_per_island())
+        {
+            if pumps_per_island != layout_pumps {
+                return Err(format!(
+                    "queues.pumps_per_island ({}) debe ser positions_per_side × sides del layout ({})",
+                    pumps_per_island, layout_pumps
+                ));
+            }
+        }
+        Ok(())
+    }
+
+    // Comprueba que una estación de `stations` surtidores pueda atender a
+    // todos los clientes del escenario. Se llama para cada cantidad que se
+    // simula: stations, comparison_stations y las de --sweep-stations.
+    pub fn validate_stations(&self, stations: usize) -> Result<(), String> {
+        self.vehicles.validate_stations(self, stations)
+    }
+
+    // Tiempo medio de carga con el caudal por defecto, para estimar el
+    // trabajo pendiente de una cola.
+    pub fn mean_refuel_time(&self) -> f64 {
+        let mean_litres = match self.dispensing.litres {
+            LitresModel::None => return self.refuel.mean,
+            LitresModel::Tank {
+                capacity,
+                fill_level,
+            } => capacity.mean * (1.0 - 0.5 * (fill_level.min + fill_level.max)),
+            LitresModel::Requested { litres } => litres.mean,
+        };
+        self.dispensing.overhead + mean_litres / self.dispensing.flow_rate
+    }
+
+    // El surtidor admite la categoría: está en su lista (si la tiene) y su
+    // caudal alcanza el mínimo exigido.
+    pub fn admits(&self, station: usize, vehicle: Vehicle) -> bool {
+        let params = self.vehicles.params(vehicle);
+        (params.pumps.is_empty() || params.pumps.contains(&station))
+            && self.dispensing.flow_rate(station) >= params.min_flow_rate
+    }
+}
+
+fn is_positive(value: f64) -> bool {
+    value.is_finite() && value > 0.0
+}
+
+impl NormalParams {
+    fn validate(&self, name: &str) -> Result<(), String> {
+        if !self.mean.is_finite() || self.std_dev.is_nan() || self.std_dev < 0.0 {
+            return Err(format!(
+                "{}: la media debe ser finita y la desviación no negativa",
+                name
+            ));
+        }
+        Ok(())
+    }
+}
+
+impl PaymentScenario {
+    pub fn params(&self, payment_method: &PaymentMethod) -> &PaymentParams {
+        match payment_method {
+            PaymentMethod::Efectivo => &self.efectivo,
+            PaymentMethod::Tarjeta => &self.tarjeta,
+            PaymentMethod::CopecApp => &self.copec_app,
+        }
+    }
+
+    fn methods(&self) -> [(&'static str, &PaymentParams); 3] {
+        [
+            ("payment.efectivo", &self.efectivo),
+            ("payment.tarjeta", &self.tarjeta),
+            ("payment.copec_app", &self.copec_app),
+        ]
+    }
+
+    fn shares(&self) -> [f64; 3] {
+        [
+            self.efectivo.share,
+            self.tarjeta.share,
+            self.copec_app.share,
+        ]
+    }
+
+    // Sorteo del método de pago según la mezcla del escenario.
+    pub fn sample_method<R: Rng>(&self, rng: &mut R) -> PaymentMethod {
+        self.sample_method_from(self.shares(), rng)
+    }
+
+    pub fn sample_method_from<R: Rng>(&self, shares: [f64; 3], rng: &mut R) -> PaymentMethod {
+        let payment_methods = [
+            PaymentMethod::Efectivo,
+            PaymentMethod::Tarjeta,
+            PaymentMethod::CopecApp,
+        ];
+        let mix = WeightedIndex::new(shares).unwrap();
+        payment_methods[mix.sample(rng)]
+    }
+}
+
+impl FuelMix {
+    fn shares(&self) -> [f64; 4] {
+        [
+            self.gasolina_93,
+            self.gasolina_95,
+            self.gasolina_97,
+            self.diesel,
+        ]
+    }
+}
+
+impl FuelScenario {
+    pub fn sample_fuel<R: Rng>(&self, rng: &mut R) -> Fuel {
+        let mix = WeightedIndex::new(self.mix.shares()).unwrap();
+        Fuel::ALL[mix.sample(rng)]
+    }
+
+    // Sorteo restringido a `fuels`, con los pesos de la mezcla general.
+    pub fn sample_fuel_among<R: Rng>(&self, fuels: &[Fuel], rng: &mut R) -> Fuel {
+        let mix = WeightedIndex::new(self.restricted_shares(fuels)).unwrap();
+        Fuel::ALL[mix.sample(rng)]
+    }
+
+    fn restricted_shares(&self, fuels: &[Fuel]) -> [f64; 4] {
+        let mut shares = self.mix.shares();
+        for (share, fuel) in shares.iter_mut().zip(Fuel::ALL) {
+            if !fuels.contains(&fuel) {
+                *share = 0.0;
+            }
+        }
+        shares
+    }
+
+    pub fn serves(&self, station: usize, fuel: Fuel) -> bool {
+        match self.pumps.get(station) {
+            Some(fuels) => fuels.contains(&fuel),
+            None => true,
+        }
+    }
+}
+
+impl FuelTanks {
+    pub fn get(&self, fuel: Fuel) -> Option<TankParams> {
+        match fuel {
+            Fuel::Gasolina93 => self.gasolina_93,
+            Fuel::Gasolina95 => self.gasolina_95,
+            Fuel::Gasolina97 => self.gasolina_97,
+            Fuel::Diesel => self.diesel,
+        }
+    }
+}
+
+impl InventoryScenario {
+    fn validate(&self, scenario: &Scenario) -> Result<(), String> {
+        let mut any_tank = false;
+        for fuel in Fuel::ALL {
+            if let Some(tank) = self.tanks.get(fuel) {
+                any_tank = true;
+                let initial = tank.initial.unwrap_or(tank.capacity);
+                if !is_positive(tank.capacity) || !(0.0..=tank.capacity).contains(&initial) {
+                    return Err(format!(
+                        "inventory.tanks.{}: la capacidad debe ser positiva y el nivel inicial estar entre 0 y ella",
+                        fuel
+                    ));
+                }
+            }
+        }
+        if !self.unloading_time.is_finite() || self.unloading_time < 0.0 {
+            return Err(String::from(
+                "inventory.unloading_time no puede ser negativo",
+            ));
+        }
+        match self.resupply {
+            Resupply::None => {}
+            Resupply::ReorderPoint {
+                reorder_level,
+                order_quantity,
+                lead_time,
+            } => {
+                if reorder_level.is_nan()
+                    || reorder_level < 0.0
+                    || !is_positive(order_quantity)
+                    || !lead_time.is_finite()
+                    || lead_time < 0.0
+                {
+                    return Err(String::from(
+                        "inventory.resupply: reorder_level y lead_time no pueden ser negativos y order_quantity debe ser positivo",
+                    ));
+                }
+            }
+            Resupply::Scheduled { first, interval } => {
+                if !first.is_finite()
+                    || first < 0.0
+                    || interval.is_nan()
+                    || interval <= self.unloading_time
+                {
+                    return Err(String::from(
+                        "inventory.resupply: first no puede ser negativo e interval debe superar a unloading_time",
+                    ));
+                }
+            }
+        }
+        // Sin litros no hay qué descontar del estanque.
+        let untracked_litres = scenario
+            .vehicles
+            .categories()
+            .iter()
+            .filter(|(_, params)| params.share > 0.0)
+            .any(|(_, params)| {
+                matches!(
+                    params.litres.unwrap_or(scenario.dispensing.litres),
+                    LitresModel::None
+                )
+            });
+        if any_tank && untracked_litres {
+            return Err(String::from(
+                "inventory.tanks requiere modelar los litros (dispensing.litres o vehicles.*.litres)",
+            ));
+        }
+        Ok(())
+    }
+}
+
+// `hour` cae en [start, start + length) horas del reloj, cruzando la medianoche.
+fn in_clock_window(hour: f64, start: f64, length: f64) -> bool {
+    (hour - start).rem_euclid(24.0) < length
+}
+
+impl Staffing {
+    pub fn limited(&self) -> bool {
+        matches!(self, Staffing::Shifts { .. })
+    }
+
+    pub fn takes_payment(&self) -> bool {
+        match self {
+            Staffing::Unlimited => false,
+            Staffing::Shifts { takes_payment, .. } => *takes_payment,
+        }
+    }
+
+    // Bomberos disponibles a la hora `hour` del reloj.
+    pub fn on_duty(&self, hour: f64) -> usize {
+        let shifts = match self {
+            Staffing::Unlimited => return usize::MAX,
+            Staffing::Shifts { shifts, .. } => shifts,
+        };
+        shifts
+            .iter()
+            .filter(|shift| shift.covers(hour))
+            .map(|shift| {
+                let on_break: usize = shift
+                    .breaks
+                    .iter()
+                    .filter(|pause| in_clock_window(hour, pause.at, pause.minutes / 60.0))
+                    .map(|pause| pause.attendants)
+                    .sum();
+                shift.attendants.saturating_sub(on_break)
+            })
+            .sum()
+    }
+
+    // Horas hasta el próximo inicio o fin de un turno o colación, a lo más 24.
+    pub fn hours_to_next_change(&self, hour: f64) -> f64 {
+        let shifts = match self {
+            Staffing::Unlimited => return 24.0,
+            Staffing::Shifts { shifts, .. } => shifts,
+        };
+        let mut boundaries = Vec::new();
+        for shift in shifts {
+            boundaries.push(shift.start);
+            boundaries.push(shift.end);
+            for pause in &shift.breaks {
+                boundaries.push(pause.at);
+                boundaries.push(pause.at + pause.minutes / 60.0);
+            }
+        }
+        boundaries
+            .iter()
+            .map(|boundary| {
+                let hours = (boundary - hour).rem_euclid(24.0);
+                // Un límite que coincide con la hora actual ya se aplicó.
+                if hours < 1e-9 {
+                    24.0
+                } else {
+                    hours
+                }
+            })
+            .fold(24.0, f64::min)
+    }
+
+    fn validate(&self) -> Result<(), String> {
+        let shifts = match self {
+            Staffing::Unlimited => return Ok(()),
+            Staffing::Shifts { shifts, .. } => shifts,
+        };
+        if shifts.is_empty() {
+            return Err(String::from("attendants.shifts no puede estar vacío"));
+        }
+        let valid_hour = |hour: f64| (0.0..=24.0).contains(&hour);
+        for shift in shifts {
+            if !valid_hour(shift.start) || !valid_hour(shift.end) || shift.attendants == 0 {
+                return Err(String::from(
+                    "attendants.shifts: start y end deben ser horas entre 0 y 24 y attendants al menos 1",
+                ));
+            }
+            for pause in &shift.breaks {
+                if !valid_hour(pause.at)
+                    || !is_positive(pause.minutes)
+                    || pause.attendants > shift.attendants
+                {
+                    return Err(String::from(
+                        "attendants.shifts.breaks: at debe ser una hora entre 0 y 24, minutes positivo y attendants no mayor que el turno",
+                    ));
+                }
+            }
+        }
+        Ok(())
+    }
+}
+
+impl Shift {
+    fn covers(&self, hour: f64) -> bool {
+        let length = (self.end - self.start).rem_euclid(24.0);
+        // start == end es un turno de 24 horas.
+        let length = if length == 0.0 { 24.0 } else { length };
+        in_clock_window(hour, self.start, length)
+    }
+}
+
+impl DurationModel {
+    pub fn sample<R: Rng>(&self, rng: &mut R) -> f64 {
+        match self {
+            DurationModel::Fixed { minutes } => *minutes,
+            DurationModel::Exponential { mean } => Exp::new(1.0 / mean).unwrap().sample(rng),
+            DurationModel::Normal { mean, std_dev } => {
+                Normal::new(*mean, *std_dev).unwrap().sample(rng).max(0.0)
+            }
+        }
+    }
+
+    fn validate(&self, name: &str) -> Result<(), String> {
+        let valid = match *self {
+            DurationModel::Fixed { minutes } => is_positive(minutes),
+            DurationModel::Exponential { mean } => is_positive(mean),
+            DurationModel::Normal { mean, std_dev } => {
+                is_positive(mean) && std_dev.is_finite() && std_dev >= 0.0
+            }
+        };
+        if valid {
+            Ok(())
+        } else {
+            Err(format!("{}: la duración media debe ser positiva", name))
+        }
+    }
+}
+
+impl Breakdowns {
+    fn validate(&self) -> Result<(), String> {
+        if let Failures::Random {
+            time_to_failure,
+            repair_time,
+        } = &self.failures
+        {
+            time_to_failure.validate("breakdowns.failures.time_to_failure")?;
+            repair_time.validate("breakdowns.failures.repair_time")?;
+        }
+        for window in &self.maintenance {
+            let valid_interval = match window.interval {
+                Some(interval) => is_positive(interval) && interval > window.duration,
+                None => true,
+            };
+            if !window.start.is_finite()
+                || window.start < 0.0
+                || !is_positive(window.duration)
+                || !valid_interval
+            {
+                return Err(format!(
+                    "breakdowns.maintenance del surtidor {}: start no puede ser negativo, duration debe ser positiva e interval mayor que duration",
+                    window.pump
+                ));
+            }
+        }
+        Ok(())
+    }
+}
+
+impl Dispensing {
+    fn validate(&self) -> Result<(), String> {
+        if !is_positive(self.flow_rate) || !self.pump_flow_rates.iter().all(|&f| is_positive(f)) {
+            return Err(String::from(
+                "los caudales de dispensing deben ser positivos",
+            ));
+        }
+        if !self.overhead.is_finite() || self.overhead < 0.0 {
+            return Err(String::from("dispensing.overhead no puede ser negativo"));
+        }
+        self.litres.validate("dispensing.litres")
+    }
+
+    pub fn sample_litres<R: Rng>(&self, rng: &mut R) -> Option<f64> {
+        self.litres.sample(rng)
+    }
+
+    pub fn flow_rate(&self, station: usize) -> f64 {
+        self.pump_flow_rates
+            .get(station)
+            .copied()
+            .unwrap_or(self.flow_rate)
+    }
+
+    pub fn refuel_time(&self, litres: f64, station: usize) -> f64 {
+        self.overhead + litres / self.flow_rate(station)
+    }
+}
+
+impl LitresModel {
+    fn validate(&self, name: &str) -> Result<(), String> {
+        match *self {
+            LitresModel::None => {}
+            LitresModel::Tank {
+                capacity,
+                fill_level,
+            } => {
+                capacity.validate(&format!("{}.capacity", name))?;
+                if !(0.0 <= fill_level.min
+                    && fill_level.min <= fill_level.max
+                    && fill_level.max <= 1.0)
+                {
+                    return Err(format!(
+                        "{}.fill_level debe cumplir 0 <= min <= max <= 1",
+                        name
+                    ));
+                }
+            }
+            LitresModel::Requested { litres } => litres.validate(&format!("{}.litres", name))?,
+        }
+        Ok(())
+    }
+
+    // Litros que carga un cliente; None si no se modelan.
+    pub fn sample<R: Rng>(&self, rng: &mut R) -> Option<f64> {
+        let litres = match *self {
+            LitresModel::None => return None,
+            LitresModel::Tank {
+                capacity,
+                fill_level,
+            } => {
+                let capacity = Normal::new(capacity.mean, capacity.std_dev)
+                    .unwrap()
+                    .sample(rng);
+                let level = Uniform::new_inclusive(fill_level.min, fill_level.max).sample(rng);
+                capacity * (1.0 - level)
+            }
+            LitresModel::Requested { litres } => Normal::new(litres.mean, litres.std_dev)
+                .unwrap()
+                .sample(rng),
+        };
+        Some(litres.max(0.0))
+    }
+}
+
+impl VehicleScenario {
+    fn categories(&self) -> [(Vehicle, &VehicleParams); 4] {
+        [
+            (Vehicle::Car, &self.car),
+            (Vehicle::Motorcycle, &self.motorcycle),
+            (Vehicle::Van, &self.van),
+            (Vehicle::Truck, &self.truck),
+        ]
+    }
+
+    pub fn params(&self, vehicle: Vehicle) -> &VehicleParams {
+        match vehicle {
+            Vehicle::Car => &self.car,
+            Vehicle::Motorcycle => &self.motorcycle,
+            Vehicle::Van => &self.van,
+            Vehicle::Truck => &self.truck,
+        }
+    }
+
+    fn shares(&self) -> [f64; 4] {
+        self.categories().map(|(_, params)| params.share)
+    }
+
+    pub fn sample_vehicle<R: Rng>(&self, rng: &mut R) -> Vehicle {
+        let mix = WeightedIndex::new(self.shares()).unwrap();
+        Vehicle::ALL[mix.sample(rng)]
+    }
+
+    fn validate(&self, scenario: &Scenario) -> Result<(), String> {
+        let shares = self.shares();
+        if shares.iter().any(|share| share.is_nan() || *share < 0.0)
+            || WeightedIndex::new(shares).is_err()
+        {
+            return Err(String::from(
+                "vehicles.*.share no puede ser negativo y su suma debe ser positiva",
+            ));
+        }
+        for (vehicle, params) in self.categories() {
+            let name = format!("vehicles.{}", vehicle.key());
+            if let Some(payment) = &params.payment {
+                let shares = payment.shares();
+                if shares.iter().any(|share| share.is_nan() || *share < 0.0)
+                    || WeightedIndex::new(shares).is_err()
+                {
+                    return Err(format!(
+                        "{}.payment no puede tener pesos negativos y su suma debe ser positiva",
+                        name
+                    ));
+                }
+            }
+            if let Some(litres) = &params.litres {
+                litres.validate(&format!("{}.litres", name))?;
+            }
+            if params.min_flow_rate.is_nan() || params.min_flow_rate < 0.0 {
+                return Err(format!("{}.min_flow_rate no puede ser negativo", name));
+            }
+            if params.share == 0.0 {
+                continue;
+            }
+            let fuel_shares = if params.fuels.is_empty() {
+                scenario.fuel.mix.shares()
+            } else {
+                scenario.fuel.restricted_shares(&params.fuels)
+            };
+            if WeightedIndex::new(fuel_shares).is_err() {
+                return Err(format!(
+                    "{}.fuels no incluye combustibles con peso positivo en fuel.mix",
+                    name
+                ));
+            }
+        }
+        Ok(())
+    }
+
+    // Con `stations` surtidores, cada combustible que puede pedir cada
+    // categoría debe tener algún surtidor que lo venda y admita al vehículo.
+    fn validate_stations(&self, scenario: &Scenario, stations: usize) -> Result<(), String> {
+        for (vehicle, params) in self.categories() {
+            if params.share == 0.0 {
+                continue;
+            }
+            let fuel_shares = if params.fuels.is_empty() {
+                scenario.fuel.mix.shares()
+            } else {
+                scenario.fuel.restricted_shares(&params.fuels)
+            };
+            for (fuel, share) in Fuel::ALL.iter().zip(fuel_shares) {
+                let served = (0..stations).any(|station| {
+                    scenario.fuel.serves(station, *fuel) && scenario.admits(station, vehicle)
+                });
+                if share > 0.0 && !served {
+                    return Err(format!(
+                        "con {} surtidores, ninguno atiende a la categoría {} con combustible {}",
+                        stations, vehicle, fuel
+                    ));
+                }
+            }
+        }
+        Ok(())
+    }
+}
+
+impl PaymentShares {
+    pub fn shares(&self) -> [f64; 3] {
+        [self.efectivo, self.tarjeta, self.copec_app]
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::{Dispensing, QueueTopology, Shift, ShiftBreak, Staffing};
+
+    #[test]
+    fn queue_topologies_group_pumps() {
+        assert_eq!(
+            QueueTopology::PerPump.queue_stations(3),
+            vec![vec![0], vec![1], vec![2]]
+        );
+        assert_eq!(QueueTopology::Shared.queue_stations(3), vec![vec![0, 1, 2]]);
+        // La última isla queda incompleta si no alcanzan los surtidores.
+        let islands = QueueTopology::Islands {
+            pumps_per_island: 2,
+        };
+        assert_eq!(
+            islands.queue_stations(5),
+            vec![vec![0, 1], vec![2, 3], vec![4]]
+        );
+    }
+
+    // Tiempo de carga = overhead + litros / caudal, con el caudal propio del
+    // surtidor o el general si no aparece en pump_flow_rates.
+    #[test]
+    fn refuel_time_is_litres_over_flow_rate_plus_overhead() {
+        let dispensing = Dispensing {
+            flow_rate: 40.0,
+            pump_flow_rates: vec![50.0, 25.0],
+            overhead: 1.5,
+            ..Dispensing::default()
+        };
+        assert_eq!(dispensing.refuel_time(50.0, 0), 1.5 + 50.0 / 50.0);
+        assert_eq!(dispensing.refuel_time(50.0, 1), 1.5 + 50.0 / 25.0);
+        assert_eq!(dispensing.refuel_time(50.0, 3), 1.5 + 50.0 / 40.0);
+        assert_eq!(dispensing.refuel_time(0.0, 1), 1.5);
+    }
+
+    // Turno de día de 7 a 19 con tres bomberos, dos de ellos en colación de 13
+    // a 14, y turno de noche de 19 a 7 (cruza la medianoche) con uno.
+    #[test]
+    fn shifts_and_breaks_set_attendants_on_duty() {
+        let staffing = Staffing::Shifts {
+            shifts: vec![
+                Shift {
+                    start: 7.0,
+                    end: 19.0,
+                    attendants: 3,
+                    breaks: vec![ShiftBreak {
+                        at: 13.0,
+                        minutes: 60.0,
+                        attendants: 2,
+                    }],
+                },
+                Shift {
+                    start: 19.0,
+                    end: 7.0,
+                    attendants: 1,
+                    breaks: Vec::new(),
+                },
+            ],
+            takes_payment: true,
+        };
+        for (hour, on_duty) in [
+            (0.5, 1),
+            (6.9, 1),
+            (7.0, 3),
+            (12.9, 3),
+            (13.0, 1),
+            (13.5, 1),
+            (14.0, 3),
+            (19.0, 1),
+            (23.5, 1),
+        ] {
+            assert_eq!(staffing.on_duty(hour), on_duty, "hora {}", hour);
+        }
+        for (hour, hours) in [
+            (0.5, 6.5),
+            (7.0, 6.0),
+            (8.0, 5.0),
+            (13.0, 1.0),
+            (13.5, 0.5),
+            (14.0, 5.0),
+            (19.0, 12.0),
+            (23.0, 8.0),
+        ] {
+            assert!(
+                (staffing.hours_to_next_change(hour) - hours).abs() < 1e-9,
+                "hora {}",
+                hour
+            );
+        }
+        assert_eq!(Staffing::Unlimited.hours_to_next_change(8.0), 24.0);
+    }
+}
@@ -0,0 +1,438 @@
+use crate::scenario::Scenario;
+use crate::Customer;
+
+use rand::rngs::StdRng;
+use rand::seq::SliceRandom;
+use rand::Rng;
+use serde::Deserialize;
+use std::fmt;
+use std::sync::Arc;
+
+// Lo que ve una política al elegir: el cliente que llega, las colas actuales
+// y qué surtidores atiende cada una. El índice 0 es el más cercano a la entrada.
+// `candidates` son las colas con algún surtidor que vende el combustible del
+// cliente; la cola elegida debe ser una de ellas.
+pub struct SelectionContext<'a> {
+    pub customer: &'a Customer,
+    pub queues: &'a [Vec<Customer>],
+    pub queue_stations: &'a [Vec<usize>],
+    pub candidates: &'a [usize],
+    pub scenario: &'a Scenario,
+}
+
+// Regla con que un cliente elige surtidor y cola. Cada corrida usa una
+// instancia nueva, así que puede guardar estado (p. ej. un turno rotativo).
+pub trait QueueSelectionPolicy: Send {
+    // Surtidor donde se atiende el cliente, entre los libres que venden su
+    // combustible (`free` no está vacío).
+    fn choose_pump(
+        &mut self,
+        free: &[usize],
+        context: &SelectionContext,
+        rng: &mut StdRng,
+    ) -> usize;
+
+    // Cola donde espera el cliente cuando no hay surtidor libre compatible.
+    fn choose_queue(&mut self, context: &SelectionContext, rng: &mut StdRng) -> usize;
+}
+
+// Política elegida en el escenario. `Custom` no se lee del TOML: se arma
+// desde código con `QueueSelection::custom`.
+#[derive(Clone, Debug, Default, Deserialize)]
+#[serde(rename_all = "snake_case")]
+pub enum QueueSelection {
+    #[default]
+    Shortest,
+    Random,
+    NearestEntrance,
+    LeastWork,
+    RoundRobin,
+    #[serde(skip)]
+    Custom(CustomPolicy),
+}
+
+type PolicyFactory = dyn Fn() -> Box<dyn QueueSelectionPolicy> + Send + Sync;
+
+#[derive(Clone)]
+pub struct CustomPolicy(Arc<PolicyFactory>);
+
+impl fmt::Debug for CustomPolicy {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(f, "CustomPolicy")
+    }
+}
+
+impl QueueSelection {
+    // `factory` se llama al inicio de cada corrida.
+    pub fn custom<F>(factory: F) -> Self
+    where
+        F: Fn() -> Box<dyn QueueSelectionPolicy> + Send + Sync + 'static,
+    {
+        QueueSelection::Custom(CustomPolicy(Arc::new(factory)))
+    }
+
+    pub fn policy(&self) -> Box<dyn QueueSelectionPolicy> {
+        match self {
+            QueueSelection::Shortest => Box::new(ShortestQueue),
+            QueueSelection::Random => Box::new(RandomQueue),
+            QueueSelection::NearestEntrance => Box::new(NearestEntrance),
+            QueueSelection::LeastWork => Box::new(LeastExpectedWork),
+            QueueSelection::RoundRobin => Box::new(RoundRobin::default()),
+            QueueSelection::Custom(CustomPolicy(factory)) => factory(),
+        }
+    }
+}
+
+// Surtidor elegido por la política, validado contra `free`. Un índice fuera
+// de la lista es un error de la política: detiene las builds de depuración y
+// en release se toma un surtidor libre al azar.
+pub fn checked_pump(
+    policy: &mut dyn QueueSelectionPolicy,
+    free: &[usize],
+    context: &SelectionContext,
+    rng: &mut StdRng,
+) -> usize {
+    let station = policy.choose_pump(free, context, rng);
+    debug_assert!(
+        free.contains(&station),
+        "la política eligió el surtidor {}, que no está entre los libres {:?}",
+        station,
+        free
+    );
+    if free.contains(&station) {
+        station
+    } else {
+        random_free_pump(free, rng)
+    }
+}
+
+// Cola elegida por la política, validada contra `context.candidates`; si no
+// es una de ellas, igual que en `checked_pump`, se usa la cola más corta.
+pub fn checked_queue(
+    policy: &mut dyn QueueSelectionPolicy,
+    context: &SelectionContext,
+    rng: &mut StdRng,
+) -> usize {
+    let queue = policy.choose_queue(context, rng);
+    debug_assert!(
+        context.candidates.contains(&queue),
+        "la política eligió la cola {}, que no está entre las candidatas {:?}",
+        queue,
+        context.candidates
+    );
+    if context.candidates.contains(&queue) {
+        queue
+    } else {
+        ShortestQueue.choose_queue(context, rng)
+    }
+}
+
+fn random_free_pump(free: &[usize], rng: &mut StdRng) -> usize {
+    *free.choose(rng).unwrap()
+}
+
+// Cola candidata con el menor valor; los empates se sortean.
+fn argmin_random_ties<R: Rng>(values: &[(usize, f64)], rng: &mut R) -> usize {
+    let mut best_indexes = Vec::new();
+    let mut best_value = f64::INFINITY;
+
+    for &(index, value) in values {
+        if value < best_value {
+            best_value = value;
+            best_indexes.clear();
+            best_indexes.push(index);
+        } else if value == best_value {
+            best_indexes.push(index);
+        }
+    }
+
+    if best_indexes.is_empty() {
+        values[rng.gen_range(0..values.len())].0
+    } else {
+        best_indexes[rng.gen_range(0..best_indexes.len())]
+    }
+}
+
+fn queue_lengths(context: &SelectionContext) -> Vec<(usize, f64)> {
+    context
+        .candidates
+        .iter()
+        .map(|&queue| (queue, context.queues[queue].len() as f64))
+        .collect()
+}
+
+// Modelo original: surtidor libre al azar; si no hay, la cola más corta con
+// empates al azar.
+pub struct ShortestQueue;
+
+impl QueueSelectionPolicy for ShortestQueue {
+    fn choose_pump(&mut self, free: &[usize], _: &SelectionContext, rng: &mut StdRng) -> usize {
+        random_free_pump(free, rng)
+    }
+
+    fn choose_queue(&mut self, context: &SelectionContext, rng: &mut StdRng) -> usize {
+        argmin_random_ties(&queue_lengths(context), rng)
+    }
+}
+
+pub struct RandomQueue;
+
+impl QueueSelectionPolicy for RandomQueue {
+    fn choose_pump(&mut self, free: &[usize], _: &SelectionContext, rng: &mut StdRng) -> usize {
+        random_free_pump(free, rng)
+    }
+
+    fn choose_queue(&mut self, context: &SelectionContext, rng: &mut StdRng) -> usize {
+        *context.candidates.choose(rng).unwrap()
+    }
+}
+
+// El conductor toma el primer surtidor libre desde la entrada y, si no hay,
+// la más cercana de las colas más cortas.
+pub struct NearestEntrance;
+
+impl QueueSelectionPolicy for NearestEntrance {
+    fn choose_pump(&mut self, free: &[usize], _: &SelectionContext, _: &mut StdRng) -> usize {
+        *free.iter().min().unwrap()
+    }
+
+    fn choose_queue(&mut self, context: &SelectionContext, _: &mut StdRng) -> usize {
+        let lengths = queue_lengths(context);
+        let shortest = lengths
+            .iter()
+            .map(|&(_, length)| length)
+            .fold(f64::INFINITY, f64::min);
+        lengths
+            .iter()
+            .find(|&&(_, length)| length == shortest)
+            .map_or(context.candidates[0], |&(queue, _)| queue)
+    }
+}
+
+// Cola con menos trabajo esperado: la suma de los tiempos medios de carga y
+// pago de quienes esperan, según su método de pago, repartida entre los
+// surtidores que atiende la cola.
+pub struct LeastExpectedWork;
+
+impl QueueSelectionPolicy for LeastExpectedWork {
+    fn choose_pump(&mut self, free: &[usize], _: &SelectionContext, rng: &mut StdRng) -> usize {
+        random_free_pump(free, rng)
+    }
+
+    fn choose_queue(&mut self, context: &SelectionContext, rng: &mut StdRng) -> usize {
+        let scenario = context.scenario;
+        let work: Vec<(usize, f64)> = context
+            .candidates
+            .iter()
+            .map(|&queue| {
+                let queued: f64 = context.queues[queue]
+                    .iter()
+                    .map(|customer| {
+                        let payment = scenario.payment.params(&customer.payment_method);
+                        scenario.mean_refuel_time() + payment.mean * scenario.payment.factor
+                    })
+                    .sum();
+                (queue, queued / context.queue_stations[queue].len() as f64)
+            })
+            .collect();
+        argmin_random_ties(&work, rng)
+    }
+}
+
+// Reparte por turnos, tanto los surtidores libres como las colas.
+#[derive(Default)]
+pub struct RoundRobin {
+    next_pump: usize,
+    next_queue: usize,
+}
+
+impl QueueSelectionPolicy for RoundRobin {
+    fn choose_pump(&mut self, free: &[usize], _: &SelectionContext, _: &mut StdRng) -> usize {
+        let station = free
+            .iter()
+            .copied()
+            .filter(|&station| station >= self.next_pump)
+            .min()
+            .unwrap_or_else(|| *free.iter().min().unwrap());
+        self.next_pump = station + 1;
+        station
+    }
+
+    fn choose_queue(&mut self, context: &SelectionContext, _: &mut StdRng) -> usize {
+        let queue = context
+            .candidates
+            .iter()
+            .copied()
+            .filter(|&queue| queue >= self.next_queue)
+            .min()
+            .unwrap_or(context.candidates[0]);
+        self.next_queue = (queue + 1) % context.queues.len();
+        queue
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::{checked_queue, QueueSelection, QueueSelectionPolicy, SelectionContext};
+    use crate::{Customer, PaymentMethod, Scenario, SimRng};
+    use rand::rngs::StdRng;
+    use rand::SeedableRng;
+    use std::collections::BTreeSet;
+
+    fn customer(id: u64, scenario: &Scenario) -> Customer {
+        let mut customer = Customer::new(id, 0.0, scenario, &mut SimRng::new(id));
+        customer.payment_method = PaymentMethod::Tarjeta;
+        customer
+    }
+
+    // Colas de largo 2, 1, 1 y 3; la cola 2 no le sirve al cliente y la 3
+    // alimenta a cuatro surtidores.
+    struct Fixture {
+        scenario: Scenario,
+        customer: Customer,
+        queues: Vec<Vec<Customer>>,
+        queue_stations: Vec<Vec<usize>>,
+        candidates: Vec<usize>,
+    }
+
+    impl Fixture {
+        fn new() -> Self {
+            let scenario = Scenario::default();
+            let queues = [2, 1, 1, 3]
+                .iter()
+                .map(|&length| (0..length).map(|id| customer(id, &scenario)).collect())
+                .collect();
+            Fixture {
+                customer: customer(99, &scenario),
+                scenario,
+                queues,
+                queue_stations: vec![vec![0], vec![1], vec![2], vec![3, 4, 5, 6]],
+                candidates: vec![0, 1, 3],
+            }
+        }
+
+        fn context(&self) -> SelectionContext<'_> {
+            SelectionContext {
+                customer: &self.customer,
+                queues: &self.queues,
+                queue_stations: &self.queue_stations,
+                candidates: &self.candidates,
+                scenario: &self.scenario,
+            }
+        }
+
+        fn queues_chosen(&self, selection: QueueSelection, draws: usize) -> Vec<usize> {
+            let mut policy = selection.policy();
+            let mut rng = StdRng::seed_from_u64(1);
+            (0..draws)
+                .map(|_| policy.choose_queue(&self.context(), &mut rng))
+                .collect()
+        }
+
+        fn pumps_chosen(&self, selection: QueueSelection, free: &[usize]) -> Vec<usize> {
+            let mut policy = selection.policy();
+            let mut rng = StdRng::seed_from_u64(1);
+            (0..4)
+                .map(|_| policy.choose_pump(free, &self.context(), &mut rng))
+                .collect()
+        }
+    }
+
+    #[test]
+    fn shortest_takes_shortest_candidate() {
+        let fixture = Fixture::new();
+        assert_eq!(
+            fixture.queues_chosen(QueueSelection::Shortest, 20),
+            vec![1; 20]
+        );
+        let pumps = fixture.pumps_chosen(QueueSelection::Shortest, &[2, 5]);
+        assert!(pumps.iter().all(|pump| [2, 5].contains(pump)));
+    }
+
+    #[test]
+    fn random_spreads_over_candidates_only() {
+        let fixture = Fixture::new();
+        let chosen: BTreeSet<usize> = fixture
+            .queues_chosen(QueueSelection::Random, 200)
+            .into_iter()
+            .collect();
+        assert_eq!(chosen, BTreeSet::from([0, 1, 3]));
+    }
+
+    #[test]
+    fn nearest_entrance_prefers_low_indexes() {
+        let mut fixture = Fixture::new();
+        assert_eq!(
+            fixture.pumps_chosen(QueueSelection::NearestEntrance, &[5, 2, 4]),
+            vec![2; 4]
+        );
+        // Con las colas 0 y 1 empatadas, la 0.
+        fixture.queues[0].pop();
+        assert_eq!(
+            fixture.queues_chosen(QueueSelection::NearestEntrance, 5),
+            vec![0; 5]
+        );
+    }
+
+    #[test]
+    fn least_work_divides_by_pumps() {
+        // La cola 3 tiene tres clientes pero cuatro surtidores: 0.75 clientes
+        // por surtidor contra 1 de la cola 1.
+        let fixture = Fixture::new();
+        assert_eq!(
+            fixture.queues_chosen(QueueSelection::LeastWork, 10),
+            vec![3; 10]
+        );
+    }
+
+    #[test]
+    fn round_robin_cycles() {
+        let fixture = Fixture::new();
+        assert_eq!(
+            fixture.queues_chosen(QueueSelection::RoundRobin, 5),
+            vec![0, 1, 3, 0, 1]
+        );
+        assert_eq!(
+            fixture.pumps_chosen(QueueSelection::RoundRobin, &[1, 3]),
+            vec![1, 3, 1, 3]
+        );
+    }
+
+    // Siempre la cola 2, que no está entre las candidatas.
+    struct WrongQueue;
+
+    impl QueueSelectionPolicy for WrongQueue {
+        fn choose_pump(&mut self, free: &[usize], _: &SelectionContext, _: &mut StdRng) -> usize {
+            free[0]
+        }
+
+        fn choose_queue(&mut self, _: &SelectionContext, _: &mut StdRng) -> usize {
+            2
+        }
+    }
+
+    #[test]
+    fn custom_policy_is_used() {
+        let fixture = Fixture::new();
+        let selection = QueueSelection::custom(|| Box::new(WrongQueue));
+        assert_eq!(fixture.queues_chosen(selection.clone(), 3), vec![2; 3]);
+        assert_eq!(fixture.pumps_chosen(selection, &[4, 6]), vec![4; 4]);
+    }
+
+    #[test]
+    #[cfg_attr(
+        debug_assertions,
+        should_panic(expected = "no está entre las candidatas")
+    )]
+    fn custom_policy_choice_is_validated() {
+        let fixture = Fixture::new();
+        let mut policy = QueueSelection::custom(|| Box::new(WrongQueue)).policy();
+        let queue = checked_queue(
+            policy.as_mut(),
+            &fixture.context(),
+            &mut StdRng::seed_from_u64(1),
+        );
+        // Sin debug_assert, se cae a la cola más corta.
+        assert_eq!(queue, 1);
+    }
+}
@@ -0,0 +1,322 @@
+use crate::attendants::AttendantStats;
+use crate::counter::Counter;
+use crate::customer::{validate_customer_data, Customer, CustomerData};
+use crate::event::{Event, EventKind};
+use crate::inventory::Tanks;
+use crate::routines::*;
+use crate::scenario::Scenario;
+use crate::state::SimState;
+use crate::stats::StationStats;
+use crate::warmup::{discard_warmup, WarmupReport};
+
+// Largo de una corrida: tiempo simulado, clientes atendidos o eventos procesados.
+#[derive(Clone, Copy, Debug)]
+pub enum RunLength {
+    Minutes(f64),
+    Days(f64),
+    Customers(u64),
+    Events(u64),
+}
+
+impl RunLength {
+    // Horizonte en minutos de reloj simulado, si la corrida se acota por tiempo.
+    pub fn horizon(&self) -> Option<f64> {
+        match self {
+            RunLength::Minutes(minutes) => Some(*minutes),
+            RunLength::Days(days) => Some(days * 1440.0),
+            RunLength::Customers(_) | RunLength::Events(_) => None,
+        }
+    }
+
+    pub fn reached(&self, events: u64, served: u64) -> bool {
+        match self {
+            RunLength::Customers(customers) => served >= *customers,
+            RunLength::Events(max_events) => events >= *max_events,
+            RunLength::Minutes(_) | RunLength::Days(_) => false,
+        }
+    }
+}
+
+// Estado del sistema al terminar una corrida.
+pub struct SimulationSummary {
+    pub end_time: f64,
+    pub events: u64,
+    pub served: u64,
+    pub in_system: u64,
+    pub in_queue: u64,
+    // Clientes que se fueron sin hacer fila.
+    pub balked: u64,
+    // Clientes que se fueron porque su combustible se agotó.
+    pub stocked_out: u64,
+    // Clientes que ningún surtidor podía atender.
+    pub unserved: u64,
+    // Clientes que abandonaron la cola.
+    pub reneged: u64,
+    // Cambios de cola y clientes que se cambiaron al menos una vez.
+    pub jockeys: u64,
+    pub jockeyed_customers: u64,
+    pub stats: StationStats,
+    pub tanks: Tanks,
+    // Uso de los bomberos, si el escenario los limita.
+    pub attendants: Option<AttendantStats>,
+    // Caja central, si algún medio de pago la usa.
+    pub counter: Option<Counter>,
+    pub warmup: Option<WarmupReport>,
+    // Error de la verificación de tiempos de los clientes, si la hubo.
+    pub inconsistency: Option<String>,
+    // Tiempo real que tomó la corrida, en segundos.
+    pub wall_secs: f64,
+}
+
+impl SimulationSummary {
+    pub fn print(&self) {
+        println!(
+            "Fin de la simulación en t = {:.2} mins tras {} eventos: {} clientes atendidos, {} aún en el sistema ({} en cola).",
+            self.end_time, self.events, self.served, self.in_system, self.in_queue
+        );
+        if self.balked > 0 {
+            println!("Clientes que se fueron sin hacer fila: {}.", self.balked);
+        }
+        if self.stocked_out > 0 {
+            println!(
+                "Clientes que se fueron por falta de combustible: {}.",
+                self.stocked_out
+            );
+        }
+        if self.unserved > 0 {
+            println!(
+                "Clientes que se fueron porque ningún surtidor vende su combustible o admite su vehículo: {}.",
+                self.unserved
+            );
+        }
+        if self.reneged > 0 {
+            println!("Clientes que abandonaron la cola: {}.", self.reneged);
+        }
+        if self.jockeys > 0 {
+            println!(
+                "Cambios de cola: {} ({} clientes se cambiaron al menos una vez).",
+                self.jockeys, self.jockeyed_customers
+            );
+        }
+        if let Some(warmup) = &self.warmup {
+            warmup.print();
+        }
+        if let Some(err) = &self.inconsistency {
+            println!(
+                "Advertencia: contabilidad de tiempos inconsistente: {}",
+                err
+            );
+        }
+        self.stats.print();
+        self.stats.print_blocking();
+        self.stats.print_availability();
+        self.tanks.print(self.end_time);
+        if let Some(utilization) = self.attendants.as_ref().and_then(|a| a.utilization()) {
+            println!("Utilización de los bomberos: {:.1}%.", utilization * 100.0);
+        }
+        if let Some(counter) = &self.counter {
+            counter.print(self.end_time);
+        }
+    }
+}
+
+pub fn simulation(
+    run_length: RunLength,
+    customer_data: &mut CustomerData,
+    fuel_station_length: usize,
+    scenario: &Scenario,
+    seed: u64,
+    warmup_end: Option<f64>,
+) -> SimulationSummary {
+    let mut state = SimState::new(fuel_station_length, scenario, seed);
+    let mut events = 0;
+    // Salidas de toda la corrida, calentamiento incluido: es lo que cuenta RunLength.
+    let mut departures = 0;
+    let mut warmed_up = false;
+
+    let initial_event = Event::new(
+        EventKind::Arrive,
+        Customer::new(0, 0.0, scenario, &mut state.rng),
+        0.0,
+    );
+    state.event_queue.push(initial_event);
+    schedule_resupply(&mut state, scenario);
+    schedule_breakdowns(&mut state, scenario);
+    schedule_staffing(&mut state, scenario);
+    // println!(
+    //     "{:<8} | {:<10} | {:<8} | {:<4} | {:<10}",
+    //     "TIEMPO", "EVENTO", "CLIENTE", "COLA", "ESTADO COLA"
+    // );
+
+    let sec = timeit_loops!(1, {
+        loop {
+            if run_length.reached(events, departures) {
+                break;
+            }
+            // Las estadísticas ponderadas por tiempo parten de cero al terminar el calentamiento.
+            if let (Some(end), Some(next)) = (warmup_end, state.event_queue.peek()) {
+                if !warmed_up && next.scheduled_time >= end {
+                    state.reset_stats(end);
+                    warmed_up = true;
+                }
+            }
+            if let (Some(horizon), Some(next)) = (run_length.horizon(), state.event_queue.peek()) {
+                if next.scheduled_time > horizon {
+                    state.sim_time = horizon;
+                    break;
+                }
+            }
+            match time_routine(&mut state) {
+                Some(mut e) => {
+                    match e.kind {
+                        EventKind::Arrive => {
+                            arrive_routine(&mut state, &mut e, scenario, customer_data);
+                        }
+                        EventKind::Queue => {
+                            queue_routine(&mut state, &mut e, scenario, customer_data);
+                        }
+                        EventKind::Refuel { station } => {
+                            refuel_routine(&mut state, &mut e, station, scenario, customer_data);
+                        }
+                        EventKind::Payment { station } => {
+                            payment_routine(&mut state, &mut e, station, scenario, customer_data);
+                        }
+                        EventKind::Departure { station } => {
+                            departures += departure_routine(
+                                &mut state,
+                                &mut e,
+                                station,
+                                scenario,
+                                customer_data,
+                            );
+                        }
+                        EventKind::Renege => {
+                            renege_routine(&mut state, &mut e, customer_data);
+                        }
+                        EventKind::Rejoin { queue } => {
+                            rejoin_routine(&mut state, &mut e, queue, scenario, customer_data);
+                        }
+                        EventKind::TankerArrival { fuel } => {
+                            tanker_arrival_routine(&mut state, fuel, scenario);
+                        }
+                        EventKind::TankerUnloaded { fuel } => {
+                            tanker_unloaded_routine(&mut state, fuel, scenario);
+                        }
+                        EventKind::PumpDown { station, cause } => {
+                            pump_down_routine(&mut state, station, cause, scenario);
+                        }
+                        EventKind::PumpUp { station, cause } => {
+                            pump_up_routine(&mut state, station, cause, scenario, customer_data);
+                        }
+                        EventKind::StaffChange => {
+                            staff_change_routine(&mut state, scenario, customer_data);
+                        }
+                        EventKind::CounterArrival { station } => {
+                            counter_arrival_routine(&mut state, &mut e, station, scenario);
+                        }
+                        EventKind::CounterDone { station } => {
+                            counter_done_routine(
+                                &mut state,
+                                &mut e,
+                                station,
+                                scenario,
+                                customer_data,
+                            );
+                        }
+                    }
+                    events += 1;
+                    /*println!(
+                        "{:<8} | {}",
+                        e.pretty_print(),
+                        format_customer_queues(&state.customer_queues)
+                    );*/
+                }
+                None => break,
+            }
+        }
+    });
+    // Si la corrida terminó antes del fin del calentamiento (por clientes o
+    // eventos), se descarta completa, también en las estadísticas ponderadas.
+    let warmup_end = warmup_end.map(|end| end.min(state.sim_time));
+    if let (Some(end), false) = (warmup_end, warmed_up) {
+        state.reset_stats(end);
+    }
+    state.stats.finish(state.sim_time);
+    state.attendants.stats.finish(state.sim_time);
+
+    // Los clientes que no alcanzaron a salir quedan con served = false,
+    // así los gráficos y promedios solo consideran clientes atendidos. Una
+    // inconsistencia detiene las builds de depuración; en release se informa
+    // en el resumen sin botar la corrida.
+    let inconsistency = validate_customer_data(customer_data).err();
+    debug_assert!(
+        inconsistency.is_none(),
+        "{}",
+        inconsistency.as_deref().unwrap_or_default()
+    );
+
+    // Todo el resumen sale de los registros posteriores al calentamiento,
+    // igual que los reportes y gráficos.
+    let warmup = warmup_end.map(|end| WarmupReport {
+        end,
+        discarded: discard_warmup(customer_data, end),
+        automatic: false,
+    });
+    let served = customer_data
+        .values()
+        .filter(|record| record.served)
+        .count() as u64;
+    let in_system = customer_data
+        .values()
+        .filter(|record| record.in_system())
+        .count() as u64;
+    let balked = customer_data
+        .values()
+        .filter(|record| record.balked)
+        .count() as u64;
+    let stocked_out = customer_data
+        .values()
+        .filter(|record| record.stocked_out)
+        .count() as u64;
+    let unserved = customer_data
+        .values()
+        .filter(|record| record.unserved)
+        .count() as u64;
+    let reneged = customer_data
+        .values()
+        .filter(|record| record.reneged)
+        .count() as u64;
+    let jockeys = customer_data
+        .values()
+        .map(|record| record.jockeys as u64)
+        .sum();
+    let jockeyed_customers = customer_data
+        .values()
+        .filter(|record| record.jockeys > 0)
+        .count() as u64;
+    let in_queue = state
+        .customer_queues
+        .iter()
+        .map(|queue| queue.len() as u64)
+        .sum();
+    SimulationSummary {
+        end_time: state.sim_time,
+        events,
+        served,
+        in_system,
+        in_queue,
+        balked,
+        stocked_out,
+        unserved,
+        reneged,
+        jockeys,
+        jockeyed_customers,
+        stats: state.stats,
+        tanks: state.tanks,
+        attendants: state.attendants.limited.then_some(state.attendants.stats),
+        counter: scenario.cashier.enabled().then_some(state.counter),
+        warmup,
+        inconsistency,
+        wall_secs: sec,
+    }
+}
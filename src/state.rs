@@ -0,0 +1,179 @@
+use crate::attendants::Attendants;
+use crate::counter::Counter;
+use crate::customer::{Fuel, Vehicle};
+use crate::inventory::Tanks;
+use crate::scenario::Scenario;
+use crate::selection::QueueSelectionPolicy;
+use crate::{Customer, EventQueue, SimRng, StationStats};
+
+// Estado mutable de una corrida, compartido por todas las rutinas.
+pub struct SimState {
+    pub event_queue: EventQueue,
+    pub sim_time: f64,
+    pub customer_count: u64,
+    // 1 si el surtidor está ocupado, 0 si está libre.
+    pub fuel_stations: Vec<i64>,
+    pub customer_queues: Vec<Vec<Customer>>,
+    // Surtidores que atiende cada cola, y la cola que alimenta a cada surtidor.
+    pub queue_stations: Vec<Vec<usize>>,
+    pub station_queue: Vec<usize>,
+    // Qué clientes puede atender cada surtidor.
+    pub station_access: Vec<PumpAccess>,
+    // Pista y posición de cada surtidor; cada pista va de adelante hacia atrás.
+    pub lanes: Vec<Vec<usize>>,
+    pub station_lane: Vec<(usize, usize)>,
+    // Cliente que terminó de pagar pero no puede salir por el auto de adelante.
+    pub blocked: Vec<Option<u64>>,
+    pub tanks: Tanks,
+    // Causas activas por las que cada surtidor está fuera de servicio.
+    pub out_of_service: Vec<u32>,
+    pub attendants: Attendants,
+    pub counter: Counter,
+    // Regla con que los clientes eligen surtidor y cola.
+    pub policy: Box<dyn QueueSelectionPolicy>,
+    pub rng: SimRng,
+    pub stats: StationStats,
+}
+
+impl SimState {
+    pub fn new(fuel_station_length: usize, scenario: &Scenario, seed: u64) -> Self {
+        let queue_stations = scenario.queues.queue_stations(fuel_station_length);
+        let lanes = scenario.layout.lanes(fuel_station_length);
+        let sides = scenario.layout.sides();
+        let mut station_lane = vec![(0, 0); fuel_station_length];
+        let mut station_position = vec![(0, 0, 0); fuel_station_length];
+        for (lane, stations) in lanes.iter().enumerate() {
+            for (position, &station) in stations.iter().enumerate() {
+                station_lane[station] = (lane, position);
+                station_position[station] = (lane / sides, lane % sides, position);
+            }
+        }
+        let mut station_queue = vec![0; fuel_station_length];
+        for (queue, stations) in queue_stations.iter().enumerate() {
+            for &station in stations {
+                station_queue[station] = queue;
+            }
+        }
+        SimState {
+            event_queue: EventQueue::new(),
+            sim_time: 0.0,
+            customer_count: 0,
+            fuel_stations: vec![0; fuel_station_length],
+            customer_queues: vec![Vec::new(); queue_stations.len()],
+            stats: StationStats::new(&station_queue, &station_position, queue_stations.len(), 0.0),
+            queue_stations,
+            station_queue,
+            station_access: (0..fuel_station_length)
+                .map(|station| PumpAccess::new(station, scenario))
+                .collect(),
+            lanes,
+            station_lane,
+            blocked: vec![None; fuel_station_length],
+            tanks: Tanks::new(&scenario.inventory),
+            out_of_service: vec![0; fuel_station_length],
+            attendants: Attendants::new(&scenario.attendants),
+            counter: Counter::new(scenario.cashier.cashiers),
+            policy: scenario.queue_selection.policy(),
+            rng: SimRng::new(seed),
+        }
+    }
+
+    // Surtidores de la misma pista delante (`ahead`) o detrás de `station`.
+    fn lane_neighbours(&self, station: usize, ahead: bool) -> &[usize] {
+        let (lane, position) = self.station_lane[station];
+        let stations = &self.lanes[lane];
+        if ahead {
+            &stations[..position]
+        } else {
+            &stations[position + 1..]
+        }
+    }
+
+    // Se puede llegar al surtidor: no hay autos detrás en su pista.
+    pub fn reachable(&self, station: usize) -> bool {
+        self.lane_neighbours(station, false)
+            .iter()
+            .all(|&behind| self.fuel_stations[behind] == 0)
+    }
+
+    // Surtidor libre, en servicio y al que se puede llegar.
+    pub fn available(&self, station: usize) -> bool {
+        self.fuel_stations[station] == 0
+            && self.out_of_service[station] == 0
+            && self.reachable(station)
+    }
+
+    // El cliente puede empezar a cargar ahora en el surtidor.
+    pub fn can_start(&self, station: usize, customer: &Customer) -> bool {
+        self.available(station)
+            && self.station_access[station].accepts(customer)
+            && self.tanks.open(customer.fuel)
+    }
+
+    // La cola tiene algún surtidor en servicio.
+    pub fn queue_in_service(&self, queue: usize) -> bool {
+        self.queue_stations[queue]
+            .iter()
+            .any(|&station| self.out_of_service[station] == 0)
+    }
+
+    // Hay un auto delante que impide salir.
+    pub fn blocked_ahead(&self, station: usize) -> bool {
+        self.lane_neighbours(station, true)
+            .iter()
+            .any(|&ahead| self.fuel_stations[ahead] == 1)
+    }
+
+    // Registra qué surtidores de la pista quedaron libres pero inaccesibles.
+    pub fn update_entry_blocking(&mut self, station: usize) {
+        let (lane, _) = self.station_lane[station];
+        for index in 0..self.lanes[lane].len() {
+            let station = self.lanes[lane][index];
+            let blocked = self.fuel_stations[station] == 0 && !self.reachable(station);
+            self.stats.entry_blocked(station, self.sim_time, blocked);
+        }
+    }
+
+    // Alguno de los surtidores que atiende la cola puede atender al cliente.
+    pub fn queue_serves(&self, queue: usize, customer: &Customer) -> bool {
+        self.queue_stations[queue]
+            .iter()
+            .any(|&station| self.station_access[station].accepts(customer))
+    }
+
+    // Fin del calentamiento: todas las estadísticas acumuladas parten de `time`.
+    pub fn reset_stats(&mut self, time: f64) {
+        self.stats.reset(time);
+        self.tanks.reset(time);
+        self.attendants.stats.reset(time);
+        self.counter.reset(time);
+    }
+}
+
+// Combustibles que vende un surtidor y categorías de vehículo que admite.
+#[derive(Clone, Debug)]
+pub struct PumpAccess {
+    pub fuels: Vec<Fuel>,
+    pub vehicles: Vec<Vehicle>,
+}
+
+impl PumpAccess {
+    fn new(station: usize, scenario: &Scenario) -> Self {
+        PumpAccess {
+            fuels: Fuel::ALL
+                .iter()
+                .copied()
+                .filter(|&fuel| scenario.fuel.serves(station, fuel))
+                .collect(),
+            vehicles: Vehicle::ALL
+                .iter()
+                .copied()
+                .filter(|&vehicle| scenario.admits(station, vehicle))
+                .collect(),
+        }
+    }
+
+    pub fn accepts(&self, customer: &Customer) -> bool {
+        self.fuels.contains(&customer.fuel) && self.vehicles.contains(&customer.vehicle)
+    }
+}
@@ -0,0 +1,600 @@
+// Estadísticas ponderadas por tiempo de colas y surtidores.
+
+use crate::event::OutageCause;
+
+// Integral en el tiempo de una magnitud que cambia a saltos (largo de una
+// cola, surtidor ocupado o no). Se actualiza en cada cambio de valor.
+#[derive(Clone, Debug)]
+pub struct TimeWeighted {
+    start: f64,
+    last_time: f64,
+    value: f64,
+    area: f64,
+    max: f64,
+}
+
+impl TimeWeighted {
+    pub fn new(start: f64) -> Self {
+        TimeWeighted {
+            start,
+            last_time: start,
+            value: 0.0,
+            area: 0.0,
+            max: 0.0,
+        }
+    }
+
+    pub fn update(&mut self, time: f64, value: f64) {
+        self.area += self.value * (time - self.last_time);
+        self.last_time = time;
+        self.value = value;
+        self.max = self.max.max(value);
+    }
+
+    // Promedio en [start, end]; el valor actual se extiende hasta `end`.
+    pub fn mean(&self, end: f64) -> f64 {
+        let elapsed = end - self.start;
+        if elapsed <= 0.0 {
+            return self.value;
+        }
+        (self.area + self.value * (end - self.last_time)) / elapsed
+    }
+
+    // Integral en [start, end], p. ej. minutos con el indicador en 1.
+    pub fn total(&self, end: f64) -> f64 {
+        self.area + self.value * (end - self.last_time)
+    }
+
+    pub fn max(&self) -> f64 {
+        self.max
+    }
+
+    // Vuelve a acumular desde `time`, conservando el valor actual.
+    pub fn restart(&mut self, time: f64) {
+        self.start = time;
+        self.last_time = time;
+        self.area = 0.0;
+        self.max = self.value;
+    }
+}
+
+#[derive(Clone, Debug)]
+pub struct PumpStats {
+    pub busy: TimeWeighted,
+    // Libre pero inaccesible por un auto detrás (1) o no (0).
+    pub entry_blocked: TimeWeighted,
+    // Minutos que los clientes esperaron para salir tras pagar.
+    pub exit_blocked_time: f64,
+    pub exit_blocked: u64,
+    // Fuera de servicio (1) o no (0), y cortes por causa.
+    pub down: TimeWeighted,
+    pub failures: u64,
+    pub maintenances: u64,
+    idle_since: Option<f64>,
+    pub idle_periods: u64,
+    pub idle_time: f64,
+    pub longest_idle: f64,
+}
+
+impl PumpStats {
+    fn new(start: f64) -> Self {
+        PumpStats {
+            busy: TimeWeighted::new(start),
+            entry_blocked: TimeWeighted::new(start),
+            exit_blocked_time: 0.0,
+            exit_blocked: 0,
+            down: TimeWeighted::new(start),
+            failures: 0,
+            maintenances: 0,
+            idle_since: Some(start),
+            idle_periods: 0,
+            idle_time: 0.0,
+            longest_idle: 0.0,
+        }
+    }
+
+    fn close_idle_period(&mut self, time: f64) {
+        if let Some(since) = self.idle_since.take() {
+            let idle = time - since;
+            self.idle_periods += 1;
+            self.idle_time += idle;
+            self.longest_idle = self.longest_idle.max(idle);
+        }
+    }
+}
+
+// Período fuera de servicio de un surtidor; `end` es None si sigue abierto.
+#[derive(Clone, Copy, Debug)]
+pub struct Outage {
+    pub station: usize,
+    pub cause: OutageCause,
+    pub start: f64,
+    pub end: Option<f64>,
+    pub redirected: u64,
+}
+
+impl Outage {
+    // El instante cae dentro del corte.
+    pub fn covers(&self, time: f64) -> bool {
+        time >= self.start && !matches!(self.end, Some(end) if time >= end)
+    }
+}
+
+// Largo de cada cola y ocupación de cada surtidor a lo largo de la corrida.
+#[derive(Clone, Debug)]
+pub struct StationStats {
+    pub queues: Vec<TimeWeighted>,
+    pub pumps: Vec<PumpStats>,
+    pub outages: Vec<Outage>,
+    // Cola que alimenta a cada surtidor, y su isla, lado y posición.
+    station_queue: Vec<usize>,
+    station_position: Vec<(usize, usize, usize)>,
+    end_time: Option<f64>,
+}
+
+impl StationStats {
+    pub fn new(
+        station_queue: &[usize],
+        station_position: &[(usize, usize, usize)],
+        queues: usize,
+        start: f64,
+    ) -> Self {
+        StationStats {
+            queues: vec![TimeWeighted::new(start); queues],
+            pumps: vec![PumpStats::new(start); station_queue.len()],
+            outages: Vec::new(),
+            station_queue: station_queue.to_vec(),
+            station_position: station_position.to_vec(),
+            end_time: None,
+        }
+    }
+
+    pub fn queue_changed(&mut self, queue: usize, time: f64, length: usize) {
+        self.queues[queue].update(time, length as f64);
+    }
+
+    pub fn pump_busy(&mut self, station: usize, time: f64) {
+        let pump = &mut self.pumps[station];
+        pump.close_idle_period(time);
+        pump.busy.update(time, 1.0);
+    }
+
+    pub fn pump_idle(&mut self, station: usize, time: f64) {
+        let pump = &mut self.pumps[station];
+        if pump.idle_since.is_none() {
+            pump.idle_since = Some(time);
+        }
+        pump.busy.update(time, 0.0);
+    }
+
+    pub fn entry_blocked(&mut self, station: usize, time: f64, blocked: bool) {
+        self.pumps[station]
+            .entry_blocked
+            .update(time, if blocked { 1.0 } else { 0.0 });
+    }
+
+    pub fn exit_blocked(&mut self, station: usize, minutes: f64) {
+        let pump = &mut self.pumps[station];
+        pump.exit_blocked += 1;
+        pump.exit_blocked_time += minutes;
+    }
+
+    // Devuelve el índice del corte en `outages`.
+    pub fn outage_started(
+        &mut self,
+        station: usize,
+        cause: OutageCause,
+        time: f64,
+        down: bool,
+    ) -> usize {
+        let pump = &mut self.pumps[station];
+        match cause {
+            OutageCause::Failure => pump.failures += 1,
+            OutageCause::Maintenance { .. } => pump.maintenances += 1,
+        }
+        pump.down.update(time, if down { 1.0 } else { 0.0 });
+        self.outages.push(Outage {
+            station,
+            cause,
+            start: time,
+            end: None,
+            redirected: 0,
+        });
+        self.outages.len() - 1
+    }
+
+    pub fn outage_ended(&mut self, station: usize, cause: OutageCause, time: f64, down: bool) {
+        self.pumps[station]
+            .down
+            .update(time, if down { 1.0 } else { 0.0 });
+        if let Some(outage) = self
+            .outages
+            .iter_mut()
+            .rev()
+            .find(|outage| outage.station == station && outage.cause == cause)
+        {
+            outage.end = Some(time);
+        }
+    }
+
+    // Cliente enviado a otra cola por el corte `outage`, el índice que
+    // devolvió `outage_started`.
+    pub fn outage_redirected(&mut self, outage: usize) {
+        self.outages[outage].redirected += 1;
+    }
+
+    pub fn availability(&self, station: usize) -> f64 {
+        let end_time = self.end_time.unwrap_or(self.pumps[station].down.last_time);
+        1.0 - self.pumps[station].down.mean(end_time)
+    }
+
+    // Colas, ocupación, bloqueos, fallas y ocio de los surtidores se cuentan
+    // desde `time`; un ocio en curso se mide a partir de ahí.
+    pub fn reset(&mut self, time: f64) {
+        for queue in self.queues.iter_mut() {
+            queue.restart(time);
+        }
+        for pump in self.pumps.iter_mut() {
+            pump.busy.restart(time);
+            pump.entry_blocked.restart(time);
+            pump.exit_blocked = 0;
+            pump.exit_blocked_time = 0.0;
+            pump.down.restart(time);
+            pump.failures = 0;
+            pump.maintenances = 0;
+            pump.idle_since = pump.idle_since.map(|_| time);
+            pump.idle_periods = 0;
+            pump.idle_time = 0.0;
+            pump.longest_idle = 0.0;
+        }
+        self.reset_outages(time);
+    }
+
+    // Los cortes que siguen abiertos parten desde `time`.
+    fn reset_outages(&mut self, time: f64) {
+        self.outages
+            .retain(|outage| !matches!(outage.end, Some(end) if end <= time));
+        for outage in self.outages.iter_mut() {
+            outage.start = outage.start.max(time);
+        }
+    }
+
+    // Cierra los períodos ociosos abiertos al terminar la corrida.
+    pub fn finish(&mut self, end_time: f64) {
+        for pump in self.pumps.iter_mut() {
+            pump.close_idle_period(end_time);
+        }
+        self.end_time = Some(end_time);
+    }
+
+    pub fn utilization(&self, station: usize) -> f64 {
+        let end_time = self.end_time.unwrap_or(self.pumps[station].busy.last_time);
+        self.pumps[station].busy.mean(end_time)
+    }
+
+    pub fn avg_queue_length(&self, queue: usize) -> f64 {
+        let end_time = self.end_time.unwrap_or(self.queues[queue].last_time);
+        self.queues[queue].mean(end_time)
+    }
+
+    pub fn print(&self) {
+        println!(
+            "{:<9} | {:>11} | {:>13} | {:>11} | {:>9} | {:>18}",
+            "SURTIDOR", "UTILIZACIÓN", "COLA PROMEDIO", "COLA MÁXIMA", "OCIOSOS", "OCIO PROM/MÁX"
+        );
+        for (station, pump) in self.pumps.iter().enumerate() {
+            let queue = self.station_queue[station];
+            let avg_idle = if pump.idle_periods > 0 {
+                pump.idle_time / pump.idle_periods as f64
+            } else {
+                0.0
+            };
+            println!(
+                "{:<9} | {:>10.1}% | {:>13.3} | {:>11} | {:>9} | {:>8.2} / {:>7.2}",
+                station,
+                self.utilization(station) * 100.0,
+                self.avg_queue_length(queue),
+                self.queues[queue].max(),
+                pump.idle_periods,
+                avg_idle,
+                pump.longest_idle
+            );
+        }
+        if self.queues.len() != self.pumps.len() {
+            println!(
+                "({} colas compartidas: cada fila muestra la cola que alimenta al surtidor.)",
+                self.queues.len()
+            );
+        }
+    }
+
+    // Disponibilidad de cada surtidor, si alguno salió de servicio.
+    pub fn print_availability(&self) {
+        if self.outages.is_empty() {
+            return;
+        }
+        println!(
+            "{:<9} | {:>14} | {:>6} | {:>12} | {:>17}",
+            "SURTIDOR", "DISPONIBILIDAD", "FALLAS", "MANTENCIONES", "FUERA SERV. (MIN)"
+        );
+        for (station, pump) in self.pumps.iter().enumerate() {
+            let end_time = self.end_time.unwrap_or(pump.down.last_time);
+            println!(
+                "{:<9} | {:>13.1}% | {:>6} | {:>12} | {:>17.1}",
+                station,
+                self.availability(station) * 100.0,
+                pump.failures,
+                pump.maintenances,
+                pump.down.total(end_time)
+            );
+        }
+    }
+
+    // Bloqueos por posición en las pistas, si hubo alguno.
+    pub fn print_blocking(&self) {
+        let any_blocking = self
+            .pumps
+            .iter()
+            .any(|pump| pump.exit_blocked > 0 || pump.entry_blocked.max() > 0.0);
+        if !any_blocking {
+            return;
+        }
+        println!(
+            "{:<9} | {:>4} | {:>4} | {:>8} | {:>15} | {:>17} | {:>15}",
+            "SURTIDOR",
+            "ISLA",
+            "LADO",
+            "POSICIÓN",
+            "SALIDAS BLOQ.",
+            "BLOQ. SALIDA PROM",
+            "ENTRADA BLOQ. %"
+        );
+        for (station, pump) in self.pumps.iter().enumerate() {
+            let (island, side, position) = self.station_position[station];
+            let end_time = self.end_time.unwrap_or(pump.entry_blocked.last_time);
+            let avg_exit_blocked = if pump.exit_blocked > 0 {
+                pump.exit_blocked_time / pump.exit_blocked as f64
+            } else {
+                0.0
+            };
+            println!(
+                "{:<9} | {:>4} | {:>4} | {:>8} | {:>15} | {:>17.3} | {:>14.1}%",
+                station,
+                island,
+                side,
+                position,
+                pump.exit_blocked,
+                avg_exit_blocked,
+                pump.entry_blocked.mean(end_time) * 100.0
+            );
+        }
+    }
+}
+
+// Media, desviación estándar muestral y semiancho del intervalo de confianza
+// t de Student para una muestra de observaciones independientes. Con una sola
+// observación no hay varianza que estimar: std_dev queda en 0, half_width en
+// NaN e `interval` devuelve None.
+#[derive(Clone, Copy, Debug)]
+pub struct Estimate {
+    pub n: usize,
+    pub mean: f64,
+    pub std_dev: f64,
+    pub half_width: f64,
+}
+
+impl Estimate {
+    pub fn from_sample(sample: &[f64], confidence: f64) -> Option<Estimate> {
+        let n = sample.len();
+        if n == 0 {
+            return None;
+        }
+        let mean = sample.iter().sum::<f64>() / n as f64;
+        if n == 1 {
+            return Some(Estimate {
+                n,
+                mean,
+                std_dev: 0.0,
+                half_width: f64::NAN,
+            });
+        }
+        let variance = sample.iter().map(|x| (x - mean).powi(2)).sum::<f64>() / (n - 1) as f64;
+        let std_dev = variance.sqrt();
+        let t = t_quantile(0.5 + confidence / 2.0, (n - 1) as f64);
+        Some(Estimate {
+            n,
+            mean,
+            std_dev,
+            half_width: t * std_dev / (n as f64).sqrt(),
+        })
+    }
+
+    pub fn lower(&self) -> f64 {
+        self.mean - self.half_width
+    }
+
+    pub fn upper(&self) -> f64 {
+        self.mean + self.half_width
+    }
+
+    pub fn interval(&self) -> Option<(f64, f64)> {
+        self.half_width
+            .is_finite()
+            .then(|| (self.lower(), self.upper()))
+    }
+}
+
+// Cuantil p de la distribución t de Student con df grados de libertad,
+// por bisección sobre la función de distribución.
+pub fn t_quantile(p: f64, df: f64) -> f64 {
+    if p < 0.5 {
+        return -t_quantile(1.0 - p, df);
+    }
+    let (mut low, mut high) = (0.0, 1.0);
+    while t_cdf(high, df) < p {
+        high *= 2.0;
+    }
+    for _ in 0..100 {
+        let mid = 0.5 * (low + high);
+        if t_cdf(mid, df) < p {
+            low = mid;
+        } else {
+            high = mid;
+        }
+    }
+    0.5 * (low + high)
+}
+
+pub fn t_cdf(t: f64, df: f64) -> f64 {
+    let tail = 0.5 * incomplete_beta(df / 2.0, 0.5, df / (df + t * t));
+    if t >= 0.0 {
+        1.0 - tail
+    } else {
+        tail
+    }
+}
+
+// Función beta incompleta regularizada I_x(a, b), por fracción continua.
+fn incomplete_beta(a: f64, b: f64, x: f64) -> f64 {
+    if x <= 0.0 {
+        return 0.0;
+    }
+    if x >= 1.0 {
+        return 1.0;
+    }
+    let front =
+        (ln_gamma(a + b) - ln_gamma(a) - ln_gamma(b) + a * x.ln() + b * (1.0 - x).ln()).exp();
+    if x < (a + 1.0) / (a + b + 2.0) {
+        front * beta_continued_fraction(a, b, x) / a
+    } else {
+        1.0 - front * beta_continued_fraction(b, a, 1.0 - x) / b
+    }
+}
+
+// Fracción continua de I_x(a, b), evaluada con el método de Lentz.
+fn beta_continued_fraction(a: f64, b: f64, x: f64) -> f64 {
+    const TINY: f64 = 1e-300;
+    let mut c = 1.0;
+    let mut d = 1.0 - (a + b) * x / (a + 1.0);
+    if d.abs() < TINY {
+        d = TINY;
+    }
+    d = 1.0 / d;
+    let mut result = d;
+    for m in 1..300 {
+        let m = m as f64;
+        let numerator = m * (b - m) * x / ((a + 2.0 * m - 1.0) * (a + 2.0 * m));
+        d = 1.0 + numerator * d;
+        d = if d.abs() < TINY { TINY } else { d };
+        c = 1.0 + numerator / c;
+        c = if c.abs() < TINY { TINY } else { c };
+        d = 1.0 / d;
+        result *= d * c;
+
+        let numerator = -(a + m) * (a + b + m) * x / ((a + 2.0 * m) * (a + 2.0 * m + 1.0));
+        d = 1.0 + numerator * d;
+        d = if d.abs() < TINY { TINY } else { d };
+        c = 1.0 + numerator / c;
+        c = if c.abs() < TINY { TINY } else { c };
+        d = 1.0 / d;
+        let delta = d * c;
+        result *= delta;
+        if (delta - 1.0).abs() < 1e-15 {
+            break;
+        }
+    }
+    result
+}
+
+// Aproximación de Lanczos de ln(Gamma(x)) para x > 0.
+fn ln_gamma(x: f64) -> f64 {
+    const COEFFICIENTS: [f64; 6] = [
+        76.18009172947146,
+        -86.50532032941677,
+        24.01409824083091,
+        -1.231739572450155,
+        0.1208650973866179e-2,
+        -0.5395239384953e-5,
+    ];
+    let mut y = x;
+    let tmp = x + 5.5;
+    let tmp = tmp - (x + 0.5) * tmp.ln();
+    let mut series = 1.000000000190015;
+    for coefficient in COEFFICIENTS {
+        y += 1.0;
+        series += coefficient / y;
+    }
+    -tmp + (2.5066282746310005 * series / x).ln()
+}
+
+#[cfg(test)]
+mod tests {
+    use super::{t_quantile, Estimate, StationStats, TimeWeighted};
+
+    #[test]
+    fn t_quantile_matches_tables() {
+        assert!((t_quantile(0.975, 9.0) - 2.262).abs() < 1e-3);
+        assert!((t_quantile(0.975, 1.0) - 12.706).abs() < 1e-3);
+        assert!((t_quantile(0.025, 9.0) + 2.262).abs() < 1e-3);
+    }
+
+    #[test]
+    fn estimate_half_width() {
+        // Media 5, varianza muestral 2.5, t(0.975, 4) = 2.776.
+        let estimate = Estimate::from_sample(&[3.0, 4.0, 5.0, 6.0, 7.0], 0.95).unwrap();
+        assert_eq!(estimate.n, 5);
+        assert!((estimate.mean - 5.0).abs() < 1e-12);
+        assert!((estimate.std_dev - 2.5f64.sqrt()).abs() < 1e-12);
+        assert!((estimate.half_width - 1.963).abs() < 1e-3);
+        let (lower, upper) = estimate.interval().unwrap();
+        assert!((lower - 3.037).abs() < 1e-3 && (upper - 6.963).abs() < 1e-3);
+    }
+
+    #[test]
+    fn estimate_single_observation_has_no_interval() {
+        let estimate = Estimate::from_sample(&[4.2], 0.95).unwrap();
+        assert_eq!(estimate.n, 1);
+        assert_eq!(estimate.mean, 4.2);
+        assert_eq!(estimate.std_dev, 0.0);
+        assert_eq!(estimate.interval(), None);
+        assert!(Estimate::from_sample(&[], 0.95).is_none());
+    }
+
+    #[test]
+    fn time_weighted_integrates_steps() {
+        // 0 en [0, 2), 3 en [2, 5), 1 en [5, 6) y 4 desde 6: área 9 + 1 + 16.
+        let mut length = TimeWeighted::new(0.0);
+        length.update(2.0, 3.0);
+        length.update(5.0, 1.0);
+        length.update(6.0, 4.0);
+        assert_eq!(length.total(10.0), 26.0);
+        assert!((length.mean(10.0) - 2.6).abs() < 1e-12);
+        assert_eq!(length.max(), 4.0);
+        // Tras reiniciar en 8 solo cuenta el valor vigente, 4.
+        length.restart(8.0);
+        assert_eq!(length.mean(10.0), 4.0);
+        assert_eq!(length.max(), 4.0);
+    }
+
+    #[test]
+    fn station_stats_track_occupancy_and_idle_periods() {
+        let mut stats = StationStats::new(&[0], &[(0, 0, 0)], 1, 0.0);
+        // Ocupado en [1, 4) y [7, 9); ocioso en [0, 1), [4, 7) y [9, 10).
+        stats.pump_busy(0, 1.0);
+        stats.pump_idle(0, 4.0);
+        stats.pump_busy(0, 7.0);
+        stats.pump_idle(0, 9.0);
+        // Dos en cola en [2, 3) y uno en [3, 4).
+        stats.queue_changed(0, 2.0, 2);
+        stats.queue_changed(0, 3.0, 1);
+        stats.queue_changed(0, 4.0, 0);
+        stats.finish(10.0);
+        assert!((stats.utilization(0) - 0.5).abs() < 1e-12);
+        assert!((stats.avg_queue_length(0) - 0.3).abs() < 1e-12);
+        assert_eq!(stats.queues[0].max(), 2.0);
+        let pump = &stats.pumps[0];
+        assert_eq!(pump.idle_periods, 3);
+        assert_eq!(pump.idle_time, 5.0);
+        assert_eq!(pump.longest_idle, 3.0);
+    }
+}
@@ -0,0 +1,166 @@
+use crate::customer::CustomerData;
+use crate::scenario::Scenario;
+use crate::simulation::{simulation, RunLength, SimulationSummary};
+
+// Período de calentamiento a descartar de las estadísticas.
+#[derive(Clone, Copy, Debug)]
+pub enum Warmup {
+    None,
+    // Largo fijo, en minutos de simulación.
+    Fixed(f64),
+    // Estimado con MSER-5 sobre los tiempos totales de los clientes.
+    Auto,
+}
+
+#[derive(Clone, Copy, Debug)]
+pub struct WarmupReport {
+    pub end: f64,
+    pub discarded: u64,
+    pub automatic: bool,
+}
+
+impl WarmupReport {
+    pub fn print(&self) {
+        println!(
+            "Calentamiento{}: se descartan los primeros {:.2} mins ({} clientes).",
+            if self.automatic { " (MSER-5)" } else { "" },
+            self.end,
+            self.discarded
+        );
+    }
+}
+
+// Elimina los registros de clientes que llegaron antes de `end`.
+pub fn discard_warmup(customer_data: &mut CustomerData, end: f64) -> u64 {
+    let before = customer_data.len();
+    customer_data.retain(|_, record| record.arrival >= end);
+    (before - customer_data.len()) as u64
+}
+
+// MSER-5: agrupa la serie en lotes de 5 y elige la cantidad d de lotes
+// iniciales a borrar que minimiza la varianza del error estándar de la media
+// de lo que queda, sum((Y_i - media)^2) / (n - d)^2, buscando solo en la
+// primera mitad. Devuelve cuántas observaciones borrar.
+pub fn mser5(series: &[f64]) -> usize {
+    let batches: Vec<f64> = series
+        .chunks_exact(5)
+        .map(|batch| batch.iter().sum::<f64>() / 5.0)
+        .collect();
+    let n = batches.len();
+    if n < 2 {
+        return 0;
+    }
+
+    // Sumas acumuladas desde el final para evaluar cada d en O(1).
+    let mut suffix_sum = vec![0.0; n + 1];
+    let mut suffix_sq = vec![0.0; n + 1];
+    for i in (0..n).rev() {
+        suffix_sum[i] = suffix_sum[i + 1] + batches[i];
+        suffix_sq[i] = suffix_sq[i + 1] + batches[i] * batches[i];
+    }
+
+    let mut best = (f64::INFINITY, 0);
+    for d in 0..=n / 2 {
+        let remaining = (n - d) as f64;
+        let sum_sq_dev = suffix_sq[d] - suffix_sum[d] * suffix_sum[d] / remaining;
+        let mser = sum_sq_dev / (remaining * remaining);
+        if mser < best.0 {
+            best = (mser, d);
+        }
+    }
+    best.1 * 5
+}
+
+// Instante de fin del calentamiento según MSER-5 sobre la serie de tiempos
+// totales de los clientes atendidos en orden de llegada: la llegada de la
+// primera observación que se conserva.
+pub fn estimate_warmup_end(customer_data: &CustomerData) -> f64 {
+    let observations: Vec<(f64, f64)> = customer_data
+        .values()
+        .filter(|record| record.served)
+        .filter_map(|record| Some((record.arrival, record.total_time()?)))
+        .collect();
+
+    let series: Vec<f64> = observations.iter().map(|&(_, total)| total).collect();
+    match mser5(&series) {
+        0 => 0.0,
+        deleted => observations[deleted].0,
+    }
+}
+
+// Corre la simulación descartando el calentamiento. Con `Warmup::Auto` se
+// corre una vez para estimar el punto de truncamiento y otra, con la misma
+// semilla y por lo tanto la misma trayectoria, para reiniciar las
+// estadísticas ponderadas por tiempo en ese instante.
+pub fn simulation_with_warmup(
+    run_length: RunLength,
+    customer_data: &mut CustomerData,
+    fuel_station_length: usize,
+    scenario: &Scenario,
+    seed: u64,
+    warmup: Warmup,
+) -> SimulationSummary {
+    match warmup {
+        Warmup::None => simulation(
+            run_length,
+            customer_data,
+            fuel_station_length,
+            scenario,
+            seed,
+            None,
+        ),
+        Warmup::Fixed(end) => simulation(
+            run_length,
+            customer_data,
+            fuel_station_length,
+            scenario,
+            seed,
+            Some(end),
+        ),
+        Warmup::Auto => {
+            let pilot = simulation(
+                run_length,
+                customer_data,
+                fuel_station_length,
+                scenario,
+                seed,
+                None,
+            );
+            let end = estimate_warmup_end(customer_data);
+            customer_data.clear();
+            let mut summary = simulation(
+                run_length,
+                customer_data,
+                fuel_station_length,
+                scenario,
+                seed,
+                Some(end),
+            );
+            summary.wall_secs += pilot.wall_secs;
+            if let Some(report) = summary.warmup.as_mut() {
+                report.automatic = true;
+            }
+            summary
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::mser5;
+
+    #[test]
+    fn mser5_cuts_transient_prefix() {
+        // 50 observaciones altas y luego un régimen estable que alterna 1.0 y 1.2.
+        let series: Vec<f64> = std::iter::repeat_n(10.0, 50)
+            .chain((0..200).map(|i| if i % 2 == 0 { 1.0 } else { 1.2 }))
+            .collect();
+        assert_eq!(mser5(&series), 50);
+    }
+
+    #[test]
+    fn mser5_keeps_stationary_series() {
+        assert_eq!(mser5(&[3.0; 100]), 0);
+        assert_eq!(mser5(&[1.0, 2.0, 3.0, 4.0, 5.0, 6.0]), 0);
+    }
+}
@@ -0,0 +1,418 @@
+use kitten_sim::replication::{run_replications, run_station_sweep, summarize};
+use kitten_sim::warmup::{simulation_with_warmup, Warmup};
+use kitten_sim::{
+    simulation, CustomerData, CustomerRecord, Fuel, RunLength, Scenario, SimulationSummary,
+};
+
+fn scenario(contents: &str) -> Scenario {
+    let scenario: Scenario = toml::from_str(contents).unwrap();
+    scenario.validate().unwrap();
+    scenario
+}
+
+fn run(
+    scenario: &Scenario,
+    minutes: f64,
+    warmup_end: Option<f64>,
+) -> (CustomerData, SimulationSummary) {
+    let mut customer_data = CustomerData::new();
+    let summary = simulation(
+        RunLength::Minutes(minutes),
+        &mut customer_data,
+        scenario.stations,
+        scenario,
+        1,
+        warmup_end,
+    );
+    assert_eq!(summary.inconsistency, None);
+    (customer_data, summary)
+}
+
+// La misma semilla reproduce la corrida completa; otra semilla no.
+#[test]
+fn same_seed_reproduces_run() {
+    let scenario = scenario("");
+    let run_with_seed = |seed| {
+        let mut customer_data = CustomerData::new();
+        simulation(
+            RunLength::Minutes(600.0),
+            &mut customer_data,
+            scenario.stations,
+            &scenario,
+            seed,
+            None,
+        );
+        format!("{:?}", customer_data)
+    };
+    assert_eq!(run_with_seed(7), run_with_seed(7));
+    assert_ne!(run_with_seed(7), run_with_seed(8));
+}
+
+// Las réplicas dan los mismos estimadores con uno o varios hilos.
+#[test]
+fn replications_do_not_depend_on_thread_count() {
+    let scenario = scenario("");
+    let estimates = |threads| {
+        let results = run_replications(
+            &scenario,
+            scenario.stations,
+            RunLength::Minutes(300.0),
+            6,
+            11,
+            threads,
+            Warmup::Fixed(60.0),
+        );
+        format!("{:?}", summarize(&results, 0.95))
+    };
+    assert_eq!(estimates(1), estimates(4));
+
+    let sweep = |threads| {
+        run_station_sweep(
+            &scenario,
+            &[3, 5],
+            RunLength::Minutes(300.0),
+            3,
+            11,
+            threads,
+            Warmup::None,
+        )
+        .iter()
+        .map(|(stations, results)| format!("{} {:?}", stations, summarize(results, 0.95)))
+        .collect::<Vec<_>>()
+    };
+    assert_eq!(sweep(1), sweep(3));
+}
+
+// Las colas por isla y el layout de islas no pueden contradecirse.
+#[test]
+fn island_queues_must_match_layout() {
+    let contents = |pumps_per_island: usize| {
+        format!(
+            r#"
+            [queues]
+            topology = "islands"
+            pumps_per_island = {}
+
+            [layout]
+            model = "islands"
+            positions_per_side = 2
+            sides = 2
+            "#,
+            pumps_per_island
+        )
+    };
+    let mismatched: Scenario = toml::from_str(&contents(2)).unwrap();
+    assert!(mismatched.validate().is_err());
+    let matching: Scenario = toml::from_str(&contents(4)).unwrap();
+    assert!(matching.validate().is_ok());
+}
+
+// Sin reposición, los que llegan o esperaban cuando se agota el estanque se
+// cuentan como perdidos por falta de stock, no como balking.
+#[test]
+fn stock_outs_are_counted_apart_from_balking() {
+    let scenario = scenario(
+        r#"
+        [arrivals]
+        scale = 0.12
+
+        [dispensing.litres]
+        model = "requested"
+        litres = { mean = 40.0, std_dev = 10.0 }
+
+        [inventory.tanks."93"]
+        capacity = 3000.0
+        "#,
+    );
+    let (customer_data, summary) = run(&scenario, 1440.0, None);
+    let tank = summary.tanks.get(Fuel::Gasolina93).unwrap();
+    assert!(summary.stocked_out > 0);
+    assert_eq!(summary.balked, 0);
+    assert_eq!(summary.stocked_out, tank.lost_customers);
+    assert!(!customer_data
+        .values()
+        .any(|record| record.fuel == Fuel::Gasolina93
+            && record.in_system()
+            && record.queue_entry.is_some()
+            && record.service_start.is_none()));
+}
+
+// La compatibilidad de combustibles se valida para cada cantidad de surtidores
+// que se simula, no solo para `stations`.
+#[test]
+fn fuel_pumps_are_validated_for_every_station_count() {
+    let contents = |comparison_stations: usize| {
+        format!(
+            r#"
+            stations = 4
+            comparison_stations = {}
+
+            [fuel]
+            pumps = [["93", "95", "97"], ["93", "95", "97"], ["diesel"], ["93", "diesel"]]
+            "#,
+            comparison_stations
+        )
+    };
+    let without_diesel: Scenario = toml::from_str(&contents(2)).unwrap();
+    assert!(without_diesel.validate().is_err());
+    let with_diesel: Scenario = toml::from_str(&contents(3)).unwrap();
+    assert!(with_diesel.validate().is_ok());
+    // Lo mismo que revisa main para cada cantidad de --sweep-stations.
+    assert!(with_diesel.validate_stations(2).is_err());
+    assert!(with_diesel.validate_stations(6).is_ok());
+}
+
+// Las restricciones de surtidor por vehículo también se validan para cada
+// cantidad de surtidores: por índice y por caudal mínimo.
+#[test]
+fn truck_pumps_are_validated_for_every_station_count() {
+    let contents = |comparison_stations: usize, restriction: &str| {
+        format!(
+            r#"
+            stations = 8
+            comparison_stations = {}
+
+            [dispensing]
+            pump_flow_rates = [40.0, 40.0, 40.0, 40.0, 40.0, 40.0, 90.0, 90.0]
+
+            [vehicles.car]
+            share = 0.9
+
+            [vehicles.truck]
+            share = 0.1
+            fuels = ["diesel"]
+            {}
+            "#,
+            comparison_stations, restriction
+        )
+    };
+    for restriction in ["pumps = [6, 7]", "min_flow_rate = 80.0"] {
+        let small: Scenario = toml::from_str(&contents(4, restriction)).unwrap();
+        assert!(small.validate().is_err(), "{}", restriction);
+        let large: Scenario = toml::from_str(&contents(7, restriction)).unwrap();
+        assert!(large.validate().is_ok(), "{}", restriction);
+        assert!(large.validate_stations(6).is_err(), "{}", restriction);
+    }
+}
+
+// Si un cliente no encuentra surtidor que venda su combustible, se va sin
+// cargar y se cuenta aparte del balking.
+#[test]
+fn customers_without_a_compatible_pump_are_not_balking() {
+    // Sin validar: con dos surtidores nadie vende diésel.
+    let scenario: Scenario = toml::from_str(
+        r#"
+        stations = 2
+
+        [fuel]
+        pumps = [["93", "95", "97"], ["93", "95", "97"]]
+
+        [balking]
+        model = "none"
+        "#,
+    )
+    .unwrap();
+    let (customer_data, summary) = run(&scenario, 600.0, None);
+    let diesel = customer_data
+        .values()
+        .filter(|record| record.fuel == Fuel::Diesel)
+        .collect::<Vec<_>>();
+    assert!(!diesel.is_empty());
+    assert!(diesel.iter().all(|record| record.unserved
+        && !record.balked
+        && !record.in_system()
+        && record.queue_entry.is_none()));
+    assert_eq!(summary.unserved as usize, diesel.len());
+    assert_eq!(summary.balked, 0);
+}
+
+// Una mantención baja la disponibilidad del surtidor y su cola queda
+// esperando la reparación ("strand") o pasa a otras colas ("redirect"); en
+// ambos casos nadie empieza a cargar en el surtidor mientras está detenido.
+#[test]
+fn outages_lower_availability_and_account_for_their_queue() {
+    let contents = |queue_policy: &str| {
+        format!(
+            r#"
+            [arrivals]
+            scale = 30.0
+
+            [breakdowns]
+            queue_policy = "{}"
+            maintenance = [{{ pump = 0, start = 720.0, duration = 120.0 }}]
+            "#,
+            queue_policy
+        )
+    };
+    for queue_policy in ["strand", "redirect"] {
+        let scenario = scenario(&contents(queue_policy));
+        let (customer_data, summary) = run(&scenario, 1440.0, None);
+        let availability = summary.stats.availability(0);
+        assert!((availability - (1.0 - 120.0 / 1440.0)).abs() < 1e-9);
+        assert_eq!(summary.stats.availability(1), 1.0);
+        let outage = &summary.stats.outages[0];
+        assert_eq!((outage.start, outage.end), (720.0, Some(840.0)));
+
+        let at_pump = |keep: &dyn Fn(f64, f64) -> bool| {
+            customer_data
+                .values()
+                .filter(|record| record.station == Some(0))
+                .filter(|record| match (record.queue_entry, record.service_start) {
+                    (Some(entry), Some(start)) => keep(entry, start),
+                    _ => false,
+                })
+                .count()
+        };
+        assert_eq!(at_pump(&|_, start| (720.0..840.0).contains(&start)), 0);
+        // Los que hacían fila al empezar el corte y cargaron en el surtidor 0
+        // después de la reparación.
+        let stranded = at_pump(&|entry, start| entry < 720.0 && start >= 840.0);
+        if queue_policy == "strand" {
+            assert_eq!(outage.redirected, 0);
+            assert!(stranded > 0);
+        } else {
+            assert!(outage.redirected > 0);
+            assert_eq!(stranded, 0);
+        }
+    }
+}
+
+// Con bomberos que cobran, quien paga en la caja suelta al bombero una sola
+// vez: al final, los bomberos tomados y no soltados son los de quienes siguen
+// cargando o cobrando en el surtidor, y los que están en la caja siguen en el
+// sistema.
+#[test]
+fn counter_payment_with_shifts_releases_attendant_once() {
+    let scenario = scenario(
+        r#"
+        [attendants]
+        model = "shifts"
+        shifts = [{ start = 0.0, end = 24.0, attendants = 3 }]
+
+        [cashier]
+        methods = ["efectivo"]
+        "#,
+    );
+    let (customer_data, summary) = run(&scenario, 600.0, None);
+    assert!(summary.served > 0);
+    assert!(customer_data
+        .values()
+        .any(|record| record.served && record.paid_at_counter));
+    let attendants = summary.attendants.unwrap();
+    let holding = customer_data
+        .values()
+        .filter(|record| {
+            record.service_start.is_some()
+                && (record.refuel_end.is_none()
+                    || (!record.paid_at_counter && record.payment_end.is_none()))
+        })
+        .count() as u64;
+    assert!(attendants.seized > 0);
+    assert_eq!(attendants.seized, attendants.released + holding);
+
+    let counter = summary.counter.unwrap();
+    let at_counter = customer_data
+        .values()
+        .filter(|record| record.paid_at_counter && record.in_system())
+        .count();
+    assert!(counter.waiting() + counter.busy <= at_counter);
+    let in_system = customer_data
+        .values()
+        .filter(|record| record.in_system())
+        .count() as u64;
+    assert_eq!(summary.in_system, in_system);
+}
+
+// El calentamiento no cambia la trayectoria: la corrida con calentamiento
+// conserva justo los clientes que llegaron después del corte, y sus conteos de
+// balking, abandonos y cambios de cola son los de esos clientes en la corrida
+// completa.
+#[test]
+fn warmup_excludes_early_customers_from_counts() {
+    let scenario = scenario(
+        r#"
+        [arrivals]
+        scale = 30.0
+
+        [balking]
+        model = "threshold"
+        length = 4
+
+        [reneging]
+        model = "exponential"
+        mean = 6.0
+
+        [jockeying]
+        model = "shorter_queue"
+        difference = 2
+        delay = 0.25
+        "#,
+    );
+    let end = 600.0;
+    let (mut full_data, full) = run(&scenario, 1440.0, None);
+    let (customer_data, summary) = run(&scenario, 1440.0, Some(end));
+    assert!(customer_data.values().all(|record| record.arrival >= end));
+
+    let discarded = full_data.len();
+    full_data.retain(|_, record| record.arrival >= end);
+    let discarded = (discarded - full_data.len()) as u64;
+    assert!(discarded > 0);
+    assert_eq!(summary.warmup.unwrap().discarded, discarded);
+
+    let count = |keep: fn(&CustomerRecord) -> bool| {
+        full_data.values().filter(|record| keep(record)).count() as u64
+    };
+    let jockeys: u64 = full_data.values().map(|record| record.jockeys as u64).sum();
+    assert_eq!(summary.served, count(|record| record.served));
+    assert_eq!(summary.balked, count(|record| record.balked));
+    assert_eq!(summary.reneged, count(|record| record.reneged));
+    assert_eq!(
+        summary.jockeyed_customers,
+        count(|record| record.jockeys > 0)
+    );
+    assert_eq!(summary.jockeys, jockeys);
+    // Hubo de todo antes del corte, así que los conteos bajan.
+    assert!(summary.balked > 0 && summary.balked < full.balked);
+    assert!(summary.reneged > 0 && summary.reneged < full.reneged);
+    assert!(summary.jockeys > 0 && summary.jockeys < full.jockeys);
+}
+
+// Un calentamiento más largo que la corrida la descarta completa, también en
+// las estadísticas ponderadas por tiempo.
+#[test]
+fn warmup_past_the_horizon_discards_everything() {
+    let scenario = scenario("");
+    let (customer_data, summary) = run(&scenario, 600.0, Some(2000.0));
+    let report = summary.warmup.unwrap();
+    assert_eq!(report.end, 600.0);
+    assert!(report.discarded > 0);
+    assert!(customer_data.is_empty());
+    assert_eq!(summary.served, 0);
+    assert!(summary
+        .stats
+        .pumps
+        .iter()
+        .all(|pump| pump.busy.total(600.0) == 0.0));
+}
+
+// La segunda corrida de Warmup::Auto repite la trayectoria de la piloto, así que
+// los clientes posteriores al corte son los mismos.
+#[test]
+fn automatic_warmup_replays_pilot_run() {
+    let scenario = scenario("");
+    let (mut pilot, _) = run(&scenario, 1440.0, None);
+    let mut customer_data = CustomerData::new();
+    let summary = simulation_with_warmup(
+        RunLength::Minutes(1440.0),
+        &mut customer_data,
+        scenario.stations,
+        &scenario,
+        1,
+        Warmup::Auto,
+    );
+    let report = summary.warmup.unwrap();
+    assert!(report.automatic);
+    assert!(report.discarded > 0);
+    pilot.retain(|_, record| record.arrival >= report.end);
+    assert_eq!(format!("{:?}", customer_data), format!("{:?}", pilot));
+}